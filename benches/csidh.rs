@@ -1,13 +1,79 @@
 #![feature(test)]
 extern crate test;
 use test::Bencher;
-use csidh;
 
-#[bench]
-fn speed(b: &mut Bencher) {
-    let mut rng = rand::thread_rng();
-    let private = csidh::CsidhPrivateKey::generate_new(&mut rng);
+use csidh::{Csidh512, CsidhParams, CsidhPrivateKey, KeySpace};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+// The key and the points the action samples are seeded, so that every iteration and every run
+// does the same work
+fn bench_public_key(b: &mut Bencher, private: CsidhPrivateKey) {
     b.iter(|| {
-        let public = private.get_public_key();
+        private.get_public_key_with_rng(&mut StdRng::seed_from_u64(1));
     });
 }
+
+fn private_key() -> CsidhPrivateKey {
+    CsidhPrivateKey::generate_new(&KeySpace::default(), &mut StdRng::seed_from_u64(0))
+}
+
+// Walking the optimal strategies instead of multiplying the point by a cofactor for every prime
+// took this from about 279ms to 228ms. That was on a single core, and without √élu the key of
+// seed 0 went from 567007 to 492506 field multiplications.
+#[bench]
+fn speed(b: &mut Bencher) {
+    bench_public_key(b, private_key());
+}
+
+#[bench]
+fn speed_edwards(b: &mut Bencher) {
+    bench_public_key(b, private_key().with_curve_model(csidh::CurveModel::Edwards));
+}
+
+#[bench]
+fn speed_without_sqrt_velu(b: &mut Bencher) {
+    bench_public_key(b, private_key().with_sqrt_velu_threshold(u64::MAX));
+}
+
+// A key with many isogenies of a single degree, which take about half of the time. Comparing
+// Vélu's and the √élu formulas for a degree shows whether √élu pays off there,
+// `CsidhPrivateKey::SQRT_VELU_THRESHOLD` is chosen from these.
+fn bench_degree(b: &mut Bencher, l: u64, sqrt_velu: bool) {
+    let mut exponents = [0; Csidh512::NUM_PRIMES];
+    exponents[Csidh512::PRIMES.iter().position(|&p| p == l).unwrap()] = 20;
+    let private = CsidhPrivateKey::from_exponents_in(&exponents, &KeySpace::uniform(20).unwrap())
+        .unwrap()
+        .with_sqrt_velu_threshold(if sqrt_velu { 0 } else { u64::MAX });
+    bench_public_key(b, private);
+}
+
+#[bench]
+fn speed_degree_131_velu(b: &mut Bencher) {
+    bench_degree(b, 131, false);
+}
+
+#[bench]
+fn speed_degree_131_sqrt_velu(b: &mut Bencher) {
+    bench_degree(b, 131, true);
+}
+
+#[bench]
+fn speed_degree_293_velu(b: &mut Bencher) {
+    bench_degree(b, 293, false);
+}
+
+#[bench]
+fn speed_degree_293_sqrt_velu(b: &mut Bencher) {
+    bench_degree(b, 293, true);
+}
+
+#[bench]
+fn speed_degree_587_velu(b: &mut Bencher) {
+    bench_degree(b, 587, false);
+}
+
+#[bench]
+fn speed_degree_587_sqrt_velu(b: &mut Bencher) {
+    bench_degree(b, 587, true);
+}
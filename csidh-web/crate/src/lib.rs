@@ -6,7 +6,7 @@ extern crate csidh;
 extern crate rand;
 
 use wasm_bindgen::prelude::*;
-use csidh::{CsidhPrivateKey};
+use csidh::{CsidhPrivateKey, KeySpace};
 
 cfg_if! {
     // When the `console_error_panic_hook` feature is enabled, we can call the
@@ -58,8 +58,8 @@ pub fn run() -> Result<(), JsValue> {
     let mut rng = rand::thread_rng();
 
     let start = now();
-    let a_private = CsidhPrivateKey::generate_new(&mut rng);
-    let b_private = CsidhPrivateKey::generate_new(&mut rng);
+    let a_private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
+    let b_private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
     let end = now();
 
     write_str(&format!("Generated private keys in {}ms", end - start));
@@ -77,16 +77,18 @@ pub fn run() -> Result<(), JsValue> {
     write_str(&format!("Generated Bob's Public Key in {}ms", end - start));
 
     let start = now();
-    let a_shared = a_private.get_shared_secret(&b_public);
+    let a_shared = a_private.get_shared_secret(&b_public).map_err(|e| e.to_string())?;
     let end = now();
 
-    write_str(&format!("Generated Alice's Shared Secret in {}ms:<br><pre>{:?}</pre>", end - start, a_shared));
+    write_str(&format!("Generated Alice's Shared Secret in {}ms:<br><pre>{:?}</pre>", end - start,
+                       a_shared.derive_key(&a_public, &b_public, b"csidh-web").as_bytes()));
 
     let start = now();
-    let b_shared = b_private.get_shared_secret(&a_public);
+    let b_shared = b_private.get_shared_secret(&a_public).map_err(|e| e.to_string())?;
     let end = now();
 
-    write_str(&format!("Generated Bob's Shared Secret in {}ms:<br><pre>{:?}</pre>", end - start, b_shared));
+    write_str(&format!("Generated Bob's Shared Secret in {}ms:<br><pre>{:?}</pre>", end - start,
+                       b_shared.derive_key(&b_public, &a_public, b"csidh-web").as_bytes()));
 
     assert_eq!(a_shared, b_shared);
     Ok(())
@@ -1,19 +1,386 @@
-use csidh::CsidhPrivateKey;
-use std::fs::File;
+//! A command line tool for CSIDH-512 key exchanges
+//!
+//! Keys are read and written as hex, or as base64 with `--base64`. Private keys are encoded like
+//! `CsidhPrivateKey::to_bytes`, public keys like `CsidhPublicKey::to_bytes`.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use csidh::{CsidhError, CsidhPrivateKey, CsidhPublicKey, KeySpace};
+use zeroize::Zeroizing;
+
+const USAGE: &str = "\
+Usage: csidh-cli [--base64 | --hex] <command> [arguments]
+
+Commands:
+    keygen [PRIVATE]                   Generate a private key and write it to a new file PRIVATE
+                                       or stdout
+    pubkey [PRIVATE]                   Print the public key of the private key in PRIVATE or stdin
+    derive [--info INFO] [--raw] PRIVATE [PUBLIC]
+                                       Print the key shared with the public key in PUBLIC or stdin
+    validate [PUBLIC]                  Check the public key in PUBLIC or stdin
+
+`derive` prints a 32 byte key derived from the shared secret, both public keys and INFO with
+HKDF-SHA-256. `--raw` prints the shared curve coefficient instead, which is not uniformly random.
+
+Exit codes:
+    0   success
+    1   the public key is not valid
+    2   the command line could not be parsed
+    3   a key could not be decoded
+    4   a file could not be read or written, or PRIVATE exists already
+    5   the group action failed on the given keys";
+
+/// Everything that makes the tool exit unsuccessfully
+#[derive(Debug)]
+enum Failure {
+    InvalidPublicKey,
+    Usage(String),
+    Decode(String),
+    Io(String, io::Error),
+    Action(String),
+}
+
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::InvalidPublicKey => 1,
+            Failure::Usage(_) => 2,
+            Failure::Decode(_) => 3,
+            Failure::Io(_, _) => 4,
+            Failure::Action(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::InvalidPublicKey => write!(f, "the public key is not valid"),
+            Failure::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            Failure::Decode(message) => write!(f, "{}", message),
+            Failure::Io(path, error) => write!(f, "{}: {}", path, error),
+            Failure::Action(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<CsidhError> for Failure {
+    fn from(error: CsidhError) -> Failure {
+        match error {
+            CsidhError::InvalidPublicKey => Failure::InvalidPublicKey,
+            CsidhError::InvalidLength { .. }
+            | CsidhError::OutOfRange
+            | CsidhError::ExponentOutOfRange { .. }
+            | CsidhError::InvalidDigit(_) => Failure::Decode(error.to_string()),
+            CsidhError::PointAtInfinity
+            | CsidhError::DegenerateCurve
+            | CsidhError::InvalidBounds { .. }
+            | CsidhError::InvalidClassGroup
+            | CsidhError::InvalidParameters
+            | CsidhError::SigningAborted => Failure::Action(error.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Hex,
+    Base64,
+}
+
+impl Encoding {
+    fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Hex => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            Encoding::Base64 => base64_encode(bytes),
+        }
+    }
+
+    fn decode(self, text: &str) -> Result<Vec<u8>, Failure> {
+        let text = text.trim();
+        match self {
+            Encoding::Hex => hex_decode(text),
+            Encoding::Base64 => base64_decode(text),
+        }
+        .ok_or_else(|| Failure::Decode(format!("the input is not valid {:?}", self)))
+    }
+}
+
+fn hex_decode(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let n = u32::from(block[0]) << 16 | u32::from(block[1]) << 8 | u32::from(block[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    for (k, chunk) in text.chunks(4).enumerate() {
+        let last = k + 1 == text.len() / 4;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+
+        let mut n = 0u32;
+        for &c in &chunk[..4 - padding] {
+            n = n << 6 | BASE64.iter().position(|&b| b == c)? as u32;
+        }
+        n <<= 6 * padding;
+
+        let block = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        // Bits that do not end up in a byte have to be zero, so that every input has one encoding
+        if block[3 - padding..].iter().any(|&b| b != 0) {
+            return None;
+        }
+        bytes.extend_from_slice(&block[..3 - padding]);
+    }
+    Some(bytes)
+}
+
+/// Reads the file at `path`, or stdin without one
+fn read_input(path: Option<&str>) -> Result<Zeroizing<String>, Failure> {
+    let mut text = Zeroizing::new(String::new());
+    match path {
+        Some(path) => {
+            *text = fs::read_to_string(path).map_err(|e| Failure::Io(path.to_string(), e))?;
+        }
+        None => {
+            io::stdin().read_to_string(&mut text).map_err(|e| Failure::Io("stdin".to_string(), e))?;
+        }
+    }
+    Ok(text)
+}
+
+/// Writes `text` and a newline to a new file at `path`, or to stdout without one
+///
+/// Files are created readable by their owner only, as they hold private keys. An existing file is
+/// an error, it might be a key that is still needed, and its permissions would be kept.
+fn write_output(path: Option<&str>, text: &str) -> Result<(), Failure> {
+    match path {
+        Some(path) => {
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+
+            options.open(path)
+                .and_then(|mut file| writeln!(file, "{}", text))
+                .map_err(|e| Failure::Io(path.to_string(), e))
+        }
+        None => {
+            writeln!(io::stdout(), "{}", text).map_err(|e| Failure::Io("stdout".to_string(), e))
+        }
+    }
+}
+
+fn read_private_key(path: Option<&str>, encoding: Encoding) -> Result<CsidhPrivateKey, Failure> {
+    let bytes = Zeroizing::new(encoding.decode(&read_input(path)?)?);
+    Ok(CsidhPrivateKey::from_bytes(&bytes, &KeySpace::default())?)
+}
+
+fn read_public_key(path: Option<&str>, encoding: Encoding) -> Result<CsidhPublicKey, Failure> {
+    let bytes = encoding.decode(&read_input(path)?)?;
+    Ok(CsidhPublicKey::from_bytes(&bytes)?)
+}
+
+/// The flags found on a command line with their values
+type Flags<'a> = Vec<(&'a str, Option<&'a str>)>;
+
+/// Splits the arguments of a command into flags and positional arguments
+///
+/// `flags` lists the flags the command knows and whether they take a value.
+fn parse<'a>(args: &'a [String], flags: &[(&str, bool)], positional: usize)
+    -> Result<(Flags<'a>, Vec<&'a str>), Failure>
+{
+    let mut found = Vec::new();
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            rest.push(arg.as_str());
+            continue;
+        }
+
+        match flags.iter().find(|(flag, _)| flag == arg) {
+            Some(&(_, true)) => {
+                let value = args.next()
+                    .ok_or_else(|| Failure::Usage(format!("{} needs a value", arg)))?;
+                found.push((arg.as_str(), Some(value.as_str())));
+            }
+            Some(&(_, false)) => found.push((arg.as_str(), None)),
+            None => return Err(Failure::Usage(format!("unknown option {}", arg))),
+        }
+    }
+
+    if rest.len() > positional {
+        return Err(Failure::Usage(format!("unexpected argument {}", rest[positional])));
+    }
+    Ok((found, rest))
+}
+
+fn run(args: &[String]) -> Result<(), Failure> {
+    let (encoding, args) = match args.first().map(String::as_str) {
+        Some("--base64") => (Encoding::Base64, &args[1..]),
+        Some("--hex") => (Encoding::Hex, &args[1..]),
+        _ => (Encoding::Hex, args),
+    };
+
+    let (command, args) = args.split_first()
+        .ok_or_else(|| Failure::Usage("no command given".to_string()))?;
+
+    match command.as_str() {
+        "keygen" => {
+            let (_, paths) = parse(args, &[], 1)?;
+            let private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rand::thread_rng());
+            write_output(paths.first().cloned(), &Zeroizing::new(encoding.encode(&private.to_bytes()[..])))
+        }
+        "pubkey" => {
+            let (_, paths) = parse(args, &[], 1)?;
+            let private = read_private_key(paths.first().cloned(), encoding)?;
+            write_output(None, &encoding.encode(&private.get_public_key().to_bytes()))
+        }
+        "derive" => {
+            let (flags, paths) = parse(args, &[("--info", true), ("--raw", false)], 2)?;
+            let private_path = paths.first()
+                .ok_or_else(|| Failure::Usage("derive needs a private key file".to_string()))?;
+            let private = read_private_key(Some(private_path), encoding)?;
+            let public = read_public_key(paths.get(1).cloned(), encoding)?;
+
+            let shared = private.get_shared_secret(&public)?;
+            if flags.iter().any(|&(flag, _)| flag == "--raw") {
+                return write_output(None, &Zeroizing::new(encoding.encode(shared.raw_coefficient_bytes())));
+            }
+
+            let info = flags.iter().rev().find_map(|&(flag, value)| value.filter(|_| flag == "--info"));
+            let key = shared.derive_key(&private.get_public_key(), &public,
+                                        info.unwrap_or("").as_bytes());
+            write_output(None, &Zeroizing::new(encoding.encode(key.as_bytes())))
+        }
+        "validate" => {
+            let (_, paths) = parse(args, &[], 1)?;
+            if read_public_key(paths.first().cloned(), encoding)?.validate() {
+                Ok(())
+            } else {
+                Err(Failure::InvalidPublicKey)
+            }
+        }
+        "help" | "--help" | "-h" => write_output(None, USAGE),
+        command => Err(Failure::Usage(format!("unknown command {}", command))),
+    }
+}
 
 fn main() {
-    let mut rng = rand::thread_rng();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(failure) = run(&args) {
+        eprintln!("csidh-cli: {}", failure);
+        process::exit(failure.exit_code());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_hex() {
+        assert_eq!(Encoding::Hex.encode(&[0x00, 0x7f, 0xff]), "007fff");
+        assert_eq!(hex_decode("007fFF"), Some(vec![0x00, 0x7f, 0xff]));
+        assert_eq!(hex_decode("007"), None);
+        assert_eq!(hex_decode("0g"), None);
+        assert_eq!(hex_decode("é0"), None);
+    }
+
+    #[test]
+    fn check_base64() {
+        let cases: [(&[u8], &str); 5] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob\xff", "Zm9vYv8="),
+        ];
+        for &(bytes, text) in cases.iter() {
+            assert_eq!(base64_encode(bytes), text);
+            assert_eq!(base64_decode(text).as_deref(), Some(bytes));
+        }
+
+        assert_eq!(base64_decode("Zg="), None);
+        assert_eq!(base64_decode("Zh=="), None);
+        assert_eq!(base64_decode("Zg==Zg=="), None);
+        assert_eq!(base64_decode("Z==="), None);
+        assert_eq!(base64_decode("Zm9*"), None);
+    }
 
-    let a_private = CsidhPrivateKey::generate_new(&mut rng);
-    let b_private = CsidhPrivateKey::generate_new(&mut rng);
+    #[test]
+    fn check_parse() {
+        let args: Vec<String> = ["a", "--raw", "--info", "x", "b"].iter().map(|s| s.to_string()).collect();
+        let flags = [("--info", true), ("--raw", false)];
+        let (found, rest) = parse(&args, &flags, 2).unwrap();
+        assert_eq!(found, vec![("--raw", None), ("--info", Some("x"))]);
+        assert_eq!(rest, vec!["a", "b"]);
 
-    let a_public = a_private.get_public_key();
-    let b_public = b_private.get_public_key();
+        assert_eq!(parse(&args, &flags, 1).unwrap_err().exit_code(), 2);
+        assert_eq!(parse(&args[..3], &flags, 2).unwrap_err().exit_code(), 2);
+        assert_eq!(parse(&args, &[], 2).unwrap_err().exit_code(), 2);
+    }
 
-    let a_shared = a_private.get_shared_secret(&b_public);
-    let b_shared = b_private.get_shared_secret(&a_public);
+    #[test]
+    fn check_failures() {
+        assert_eq!(run(&[]).unwrap_err().exit_code(), 2);
+        assert_eq!(run(&["frobnicate".to_string()]).unwrap_err().exit_code(), 2);
+        assert_eq!(run(&["derive".to_string()]).unwrap_err().exit_code(), 2);
+        assert_eq!(run(&["validate".to_string(), "/nonexistent/key".to_string()]).unwrap_err().exit_code(), 4);
+        assert_eq!(Failure::from(CsidhError::OutOfRange).exit_code(), 3);
+        assert_eq!(Failure::from(CsidhError::ExponentOutOfRange { index: 0 }).exit_code(), 3);
+        assert_eq!(Failure::from(CsidhError::DegenerateCurve).exit_code(), 5);
+    }
 
+    #[test]
+    fn check_keygen_keeps_files() {
+        let path = std::env::temp_dir().join(format!("csidh-cli-test-{}", process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::write(&path, "old key\n").unwrap();
 
-    assert_eq!(a_shared, b_shared);
-    println!("You have a common secret!: \n{:?}", a_shared);
+        let result = run(&["keygen".to_string(), path.clone()]);
+        let kept = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap_err().exit_code(), 4);
+        assert_eq!(kept, "old key\n");
+    }
 }
@@ -0,0 +1,602 @@
+//! The structure of the class group the CSIDH group action is defined over
+//!
+//! The exponent vectors `e` with `l_1^e_1 * ... * l_n^e_n = 1` form the relation lattice `L`, whose
+//! determinant is the class number `h`. When the class group is cyclic and generated by the ideal
+//! `l_1` above the first prime, as for CSIDH-512, every integer `a` modulo `h` stands for the class
+//! of `l_1^a`. Reducing the vector `(a, 0, ..., 0)` modulo a reduced basis of `L` gives a short
+//! exponent vector of the same class, which the group action can evaluate.
+
+use rand::prelude::*;
+
+use crate::error::CsidhError;
+use crate::galois::LargeUint;
+use crate::params::{Array, Csidh512, CsidhParams};
+
+/// The class number of CSIDH-512, `3 * 37 * 1407181 * 51593604295295867744293584889 *
+/// 31599414504681995853008278745587832204909`, as computed by Beullens, Kleinjung and Vercauteren
+const CSIDH512_CLASS_NUMBER: [u64; 8] = [
+    0x4291aa03cd95356f, 0xdf68a8029b289f12, 0x0c6dbd5a6a941df1, 0x33002cb20d405a4f,
+    0x0000000000000002, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+];
+
+/// The primes the inverse of the basis is computed modulo, the largest ones below `2^62`
+const MODULI: [u64; 16] = [
+    (1 << 62) - 57, (1 << 62) - 87, (1 << 62) - 117, (1 << 62) - 143, (1 << 62) - 153,
+    (1 << 62) - 167, (1 << 62) - 171, (1 << 62) - 195, (1 << 62) - 203, (1 << 62) - 273,
+    (1 << 62) - 287, (1 << 62) - 317, (1 << 62) - 443, (1 << 62) - 483, (1 << 62) - 495,
+    (1 << 62) - 575,
+];
+
+fn mul_mod_u64(a: u64, b: u64, q: u64) -> u64 {
+    (a as u128 * b as u128 % q as u128) as u64
+}
+
+fn inverse_mod_u64(a: u64, q: u64) -> u64 {
+    let mut ret = 1;
+    let mut base = a;
+    let mut e = q - 2;
+    while e > 0 {
+        if e & 1 == 1 {
+            ret = mul_mod_u64(ret, base, q);
+        }
+        base = mul_mod_u64(base, base, q);
+        e >>= 1;
+    }
+    ret
+}
+
+fn rem_u64<P: CsidhParams>(a: &LargeUint<P>, q: u64) -> u64 {
+    a.elements.as_ref().iter().rev()
+        .fold(0, |acc, &limb| (((acc as u128) << 64 | limb as u128) % q as u128) as u64)
+}
+
+fn to_f64<P: CsidhParams>(a: &LargeUint<P>) -> f64 {
+    a.elements.as_ref().iter().rev().fold(0.0, |acc, &limb| acc * 2f64.powi(64) + limb as f64)
+}
+
+/// The class group of the parameter set `P`, given by its class number and a reduced basis of the
+/// relation lattice
+///
+/// The class group has to be cyclic and generated by the ideal above the first prime. Beullens,
+/// Kleinjung and Vercauteren computed this structure for CSIDH-512 when they introduced CSI-FiSh,
+/// the class number is built in, their reduced basis has to be passed to `ClassGroup::csidh512`.
+/// The basis is not distributed with the crate. It is the result of a class group computation
+/// that cannot be redone from anything in here, and a copy has to be taken from their
+/// publication. Its rows can be checked to be relations by acting with them on `E_0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassGroup<P: CsidhParams = Csidh512> {
+    class_number: LargeUint<P>,
+    // The rows of the reduced basis
+    basis: Vec<P::Exponents>,
+    // The first row of h * B^-1 modulo h, (a, 0, ..., 0) has the coordinates a * inverse / h
+    inverse: Vec<LargeUint<P>>,
+    // The largest absolute value of each exponent of a reduced vector
+    bounds: P::Exponents,
+}
+
+impl ClassGroup<Csidh512> {
+    /// The class group of CSIDH-512 with the given reduced basis of its relation lattice
+    pub fn csidh512(basis: &[i8]) -> Result<ClassGroup<Csidh512>, CsidhError> {
+        let class_number = LargeUint { elements: CSIDH512_CLASS_NUMBER };
+        ClassGroup::from_class_number(class_number, basis)
+    }
+}
+
+impl<P: CsidhParams> ClassGroup<P> {
+    /// Creates the class group from its class number and a basis of the relation lattice
+    ///
+    /// The class number is encoded in little-endian, the basis has `NUM_PRIMES` rows of
+    /// `NUM_PRIMES` exponents each, one after the other. The determinant of the basis has to be the
+    /// class number and the basis has to be reduced well enough that the reduced vectors fit into
+    /// exponents. That its rows are relations cannot be checked here.
+    pub fn new(class_number: &[u8], basis: &[i8]) -> Result<ClassGroup<P>, CsidhError> {
+        let bytes = <P::Bytes as Array<u8>>::LEN;
+        if class_number.len() > bytes {
+            return Err(CsidhError::InvalidLength {
+                expected: bytes,
+                actual: class_number.len(),
+            });
+        }
+
+        let mut padded = P::Bytes::filled(0);
+        padded.as_mut()[..class_number.len()].copy_from_slice(class_number);
+        ClassGroup::from_class_number(LargeUint::from_bytes(padded.as_ref()), basis)
+    }
+
+    fn from_class_number(class_number: LargeUint<P>, basis: &[i8])
+        -> Result<ClassGroup<P>, CsidhError>
+    {
+        let n = P::NUM_PRIMES;
+        if basis.len() != n * n {
+            return Err(CsidhError::InvalidLength {
+                expected: n * n,
+                actual: basis.len(),
+            });
+        }
+
+        if class_number.bits() < 2 || class_number >= (LargeUint { elements: P::P }) {
+            return Err(CsidhError::InvalidClassGroup);
+        }
+
+        let rows: Vec<P::Exponents> = basis.chunks(n).map(|chunk| {
+            let mut row = P::Exponents::filled(0);
+            row.as_mut().copy_from_slice(chunk);
+            row
+        }).collect();
+
+        // The reduced vectors are combinations of the rows with coefficients of at most 1/2
+        let mut bounds = P::Exponents::filled(0);
+        for i in 0..n {
+            let sum = rows.iter().map(|row| (row[i] as i32).abs()).sum::<i32>();
+            if sum > 2 * i8::MAX as i32 {
+                return Err(CsidhError::InvalidClassGroup);
+            }
+            bounds[i] = ((sum + 1) / 2) as i8;
+        }
+
+        let mut group = ClassGroup {
+            class_number,
+            basis: rows,
+            inverse: Vec::new(),
+            bounds,
+        };
+        group.inverse = group.inverse_row().ok_or(CsidhError::InvalidClassGroup)?;
+
+        Ok(group)
+    }
+
+    /// The class number in little-endian
+    pub fn class_number(&self) -> Vec<u8> {
+        self.class_number.as_bytes().as_ref()[..self.element_bytes()].to_vec()
+    }
+
+    /// The bounds on the absolute values of the exponents `reduce` returns
+    pub(crate) fn bounds(&self) -> &P::Exponents {
+        &self.bounds
+    }
+
+    /// The number of bytes an element modulo the class number is encoded with
+    pub(crate) fn element_bytes(&self) -> usize {
+        self.class_number.bits().div_ceil(8) as usize
+    }
+
+    /// Encodes an element modulo the class number in little-endian
+    pub(crate) fn element_to_bytes(&self, a: &LargeUint<P>) -> Vec<u8> {
+        a.as_bytes().as_ref()[..self.element_bytes()].to_vec()
+    }
+
+    /// Decodes an element encoded by `element_to_bytes`, it has to be smaller than the class number
+    pub(crate) fn element_from_bytes(&self, bytes: &[u8]) -> Option<LargeUint<P>> {
+        if bytes.len() != self.element_bytes() {
+            return None;
+        }
+
+        let mut padded = P::Bytes::filled(0);
+        padded.as_mut()[..bytes.len()].copy_from_slice(bytes);
+        let a = LargeUint::from_bytes(padded.as_ref());
+        if a >= self.class_number {
+            return None;
+        }
+
+        Some(a)
+    }
+
+    /// Samples an element modulo the class number uniformly at random
+    pub(crate) fn random_element<R: CryptoRng + Rng>(&self, rng: &mut R) -> LargeUint<P> {
+        let bits = self.class_number.bits();
+        loop {
+            let mut a = LargeUint::<P>::from_u64(0);
+            for i in 0..P::LIMBS {
+                let top = bits as i64 - 64 * i as i64;
+                if top > 0 {
+                    a.elements[i] = rng.gen::<u64>() >> (64 - top.min(64));
+                }
+            }
+
+            if a < self.class_number {
+                return a;
+            }
+        }
+    }
+
+    /// `a + b` modulo the class number
+    pub(crate) fn add(&self, a: &LargeUint<P>, b: &LargeUint<P>) -> LargeUint<P> {
+        let mut ret = *a;
+        let carry = ret.add_from(b);
+        if carry || ret >= self.class_number {
+            ret.sub_from(&self.class_number);
+        }
+        ret
+    }
+
+    /// `a - b` modulo the class number
+    pub(crate) fn sub(&self, a: &LargeUint<P>, b: &LargeUint<P>) -> LargeUint<P> {
+        let mut ret = *a;
+        if ret.sub_from(b) {
+            ret.add_from(&self.class_number);
+        }
+        ret
+    }
+
+    /// `a * b` modulo the class number, for any `b`
+    fn mul(&self, a: &LargeUint<P>, b: &LargeUint<P>) -> LargeUint<P> {
+        let mut ret = LargeUint::from_u64(0);
+        for i in (0..b.bits()).rev() {
+            ret = self.add(&ret, &ret);
+            if b.bit(i) {
+                ret = self.add(&ret, a);
+            }
+        }
+        ret
+    }
+
+    /// A short exponent vector of the class of `l_1^a`
+    ///
+    /// This is Babai's rounding: `(a, 0, ..., 0)` is written in the basis, and the vector that is
+    /// left after rounding the coordinates to integers is the result.
+    pub(crate) fn reduce(&self, a: &LargeUint<P>) -> P::Exponents {
+        let h = to_f64(&self.class_number);
+        let mut acc = vec![0f64; P::NUM_PRIMES];
+
+        for (row, w) in self.basis.iter().zip(self.inverse.iter()) {
+            // The fractional part of the coordinate, between -1/2 and 1/2
+            let m = self.mul(a, w);
+            let minus_m = self.sub(&LargeUint::from_u64(0), &m);
+            let frac = if m > minus_m {
+                -to_f64(&minus_m) / h
+            } else {
+                to_f64(&m) / h
+            };
+
+            for (x, &b) in acc.iter_mut().zip(row.as_ref().iter()) {
+                *x += frac * b as f64;
+            }
+        }
+
+        let mut ret = P::Exponents::filled(0);
+        for (e, x) in ret.as_mut().iter_mut().zip(acc.iter()) {
+            *e = x.round() as i8;
+        }
+        ret
+    }
+
+    /// The first row of `h * B^-1` modulo `h`, or `None` if the determinant of `B` is not `+-h`
+    ///
+    /// The row is computed exactly modulo enough word sized primes to fit the Hadamard bound on the
+    /// minors of `B`, then combined with Garner's algorithm and reduced modulo `h`.
+    fn inverse_row(&self) -> Option<Vec<LargeUint<P>>> {
+        let n = self.basis.len();
+
+        // Both the determinant and the entries of the adjugate are bounded by the product of the row
+        // norms, the residues have to determine them including the sign
+        let bound: f64 = self.basis.iter()
+            .map(|row| row.as_ref().iter().map(|&b| (b as f64).powi(2)).sum::<f64>().sqrt().max(1.0))
+            .map(f64::log2)
+            .sum();
+
+        let mut moduli = Vec::new();
+        let mut residues: Vec<Vec<u64>> = Vec::new();
+        let mut sign = None;
+        let mut bits = 0.0;
+        for &q in MODULI.iter() {
+            if bits > bound + 2.0 {
+                break;
+            }
+
+            let h = rem_u64(&self.class_number, q);
+
+            // Solve B^T x = e_1 and compute det B by Gaussian elimination on [B^T | e_1]
+            let mut m: Vec<Vec<u64>> = (0..n)
+                .map(|i| {
+                    let mut col: Vec<u64> = self.basis.iter()
+                        .map(|row| (row[i] as i64).rem_euclid(q as i64) as u64)
+                        .collect();
+                    col.push((i == 0) as u64);
+                    col
+                })
+                .collect();
+
+            let mut det = 1u64;
+            let mut singular = false;
+            for c in 0..n {
+                let pivot = match (c..n).find(|&r| m[r][c] != 0) {
+                    Some(r) => r,
+                    None => {
+                        singular = true;
+                        break;
+                    }
+                };
+                if pivot != c {
+                    m.swap(pivot, c);
+                    det = q - det;
+                }
+
+                det = mul_mod_u64(det, m[c][c], q);
+                let inv = inverse_mod_u64(m[c][c], q);
+                for x in m[c].iter_mut() {
+                    *x = mul_mod_u64(*x, inv, q);
+                }
+
+                for r in 0..n {
+                    if r != c && m[r][c] != 0 {
+                        let f = m[r][c];
+                        for k in c..=n {
+                            let t = mul_mod_u64(f, m[c][k], q);
+                            m[r][k] = (m[r][k] + q - t) % q;
+                        }
+                    }
+                }
+            }
+
+            // A modulus dividing the determinant says nothing about the inverse, skip it
+            if singular {
+                continue;
+            }
+
+            let this_sign = if det == h {
+                true
+            } else if det == (q - h) % q {
+                false
+            } else {
+                return None;
+            };
+            if *sign.get_or_insert(this_sign) != this_sign {
+                return None;
+            }
+
+            // w = h * x, as w B = h e_1
+            residues.push(m.iter().map(|row| mul_mod_u64(h, row[n], q)).collect());
+            moduli.push(q);
+            bits += (q as f64).log2();
+        }
+
+        if bits <= bound + 2.0 {
+            return None;
+        }
+
+        // The product of the moduli so far, modulo h
+        let mut products = vec![LargeUint::from_u64(1)];
+        for &q in moduli.iter() {
+            let last = products[products.len() - 1];
+            products.push(self.mul(&last, &LargeUint::from_u64(q)));
+        }
+
+        let inverse = (0..n).map(|j| {
+            // The digits of w_j in the mixed radix of the moduli
+            let mut digits: Vec<u64> = Vec::with_capacity(moduli.len());
+            for (k, &q) in moduli.iter().enumerate() {
+                let mut t = residues[k][j];
+                for (&d, &other) in digits.iter().zip(moduli.iter()) {
+                    t = mul_mod_u64((t + q - d % q) % q, inverse_mod_u64(other % q, q), q);
+                }
+                digits.push(t);
+            }
+
+            // w_j is negative if its digits exceed those of (Q - 1) / 2, which are all (q - 1) / 2
+            let negative = digits.iter().zip(moduli.iter()).rev()
+                .map(|(&d, &q)| d.cmp(&((q - 1) / 2)))
+                .find(|&o| o != std::cmp::Ordering::Equal) == Some(std::cmp::Ordering::Greater);
+
+            let mut w = LargeUint::from_u64(0);
+            for (d, product) in digits.iter().zip(products.iter()) {
+                w = self.add(&w, &self.mul(product, &LargeUint::from_u64(*d)));
+            }
+            if negative {
+                w = self.sub(&w, &products[moduli.len()]);
+            }
+            w
+        }).collect();
+
+        Some(inverse)
+    }
+}
+
+#[cfg(test)]
+impl ClassGroup<crate::params::Toy> {
+    /// The class group of the toy parameter set
+    ///
+    /// The ideals above 5, 7 and 47 are those of `l_1^22`, `l_1^28` and `l_1^10`, the basis is
+    /// made of short vectors of the lattice of relations this gives.
+    pub(crate) fn toy() -> ClassGroup<crate::params::Toy> {
+        let basis = [
+            2, -1, 0, 2,
+            2, 2, -2, 1,
+            2, 0, 1, -3,
+            1, 4, 3, 1,
+        ];
+        ClassGroup::new(&[183], &basis).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::csidh::{action, IsogenyConfig};
+    use crate::galois::GaloisElement;
+    use crate::montgomery::Curve;
+    use crate::params::Toy;
+
+    fn toy_action(a: LargeUint<Toy>, e: &[i8; 4]) -> LargeUint<Toy> {
+        let curve = Curve::<GaloisElement<Toy>>::new(a, 1u32.into());
+        action(&curve, e, IsogenyConfig::default(), &mut thread_rng()).unwrap()
+    }
+
+    #[test]
+    fn check_class_number() {
+        let h = LargeUint::<Csidh512>::parse_bytes(
+            b"254652442229484275177030186010639202161620514305486423592570860975597611726191"
+        ).unwrap();
+        assert_eq!(h.elements, CSIDH512_CLASS_NUMBER);
+    }
+
+    #[test]
+    fn check_toy() {
+        let group = ClassGroup::toy();
+
+        // The powers of l_1 run through all 183 curves before they come back to E_0
+        let mut curves = vec![LargeUint::<Toy>::from_u64(0)];
+        for _ in 1..183 {
+            let next = toy_action(*curves.last().unwrap(), &[1, 0, 0, 0]);
+            assert!(!curves.contains(&next));
+            curves.push(next);
+        }
+        assert_eq!(toy_action(curves[182], &[1, 0, 0, 0]), curves[0]);
+
+        for row in group.basis.iter() {
+            assert_eq!(toy_action(curves[0], row), curves[0]);
+        }
+
+        for (a, curve) in curves.iter().enumerate() {
+            let e = group.reduce(&LargeUint::from_u64(a as u64));
+            assert!(e.iter().all(|e| e.abs() <= 6));
+            assert!(e.iter().zip(group.bounds.iter()).all(|(e, bound)| e.abs() <= *bound));
+            assert_eq!(toy_action(curves[0], &e), *curve, "a = {}", a);
+        }
+    }
+
+    // The basis of CSIDH-512 is not part of the crate, run this with `cargo test -- --ignored` and
+    // CSIDH512_BASIS set to a file with its exponents as bytes
+    #[test]
+    #[ignore]
+    fn check_csidh512() {
+        let path = std::env::var("CSIDH512_BASIS").expect("CSIDH512_BASIS is not set");
+        let basis: Vec<i8> = std::fs::read(path).unwrap().into_iter().map(|b| b as i8).collect();
+        let group = ClassGroup::csidh512(&basis).unwrap();
+
+        let mut rng = thread_rng();
+        let act = |a: LargeUint<Csidh512>, e: &[i8; 74]| {
+            let curve = Curve::<GaloisElement<Csidh512>>::new(a, 1u32.into());
+            action(&curve, e, IsogenyConfig::public(), &mut thread_rng()).unwrap()
+        };
+        let e_0 = LargeUint::from_u64(0);
+
+        for row in group.basis.iter() {
+            assert_eq!(act(e_0, row), e_0);
+        }
+
+        // [h] E_0 = E_0, with the vector of h - 1 and one more isogeny of degree 3
+        let mut l_1 = [0i8; 74];
+        l_1[0] = 1;
+        let minus_one = group.sub(&e_0, &LargeUint::from_u64(1));
+        assert_eq!(act(act(e_0, &group.reduce(&minus_one)), &l_1), e_0);
+        assert_eq!(act(e_0, &group.reduce(&LargeUint::from_u64(1))), act(e_0, &l_1));
+
+        for _ in 0..3 {
+            let (a, b) = (group.random_element(&mut rng), group.random_element(&mut rng));
+            let (e_a, e_b) = (group.reduce(&a), group.reduce(&b));
+            assert!(e_a.iter().zip(group.bounds.iter()).all(|(e, bound)| e.abs() <= *bound));
+            assert_eq!(act(act(e_0, &e_a), &e_b), act(e_0, &group.reduce(&group.add(&a, &b))));
+        }
+    }
+
+    #[test]
+    fn check_reduce() {
+        // An upper bidiagonal basis with 3^37 as its determinant, the inverse has entries of both
+        // signs that need several moduli
+        let mut rng = thread_rng();
+        let n = Csidh512::NUM_PRIMES;
+        let mut basis = vec![0i8; n * n];
+        let mut h = LargeUint::<Csidh512>::from_u64(1);
+        for j in 0..n {
+            basis[j * n + j] = if j % 2 == 0 { 3 } else { 1 };
+            if basis[j * n + j] == 3 {
+                h.mul_with_u64(3);
+            }
+            if j + 1 < n {
+                basis[j * n + j + 1] = (j * 7 % 3) as i8 - 1;
+            }
+        }
+
+        let group = ClassGroup::<Csidh512>::new(&h.as_bytes()[..24], &basis).unwrap();
+        assert_eq!(group.class_number().len(), 8);
+
+        for _ in 0..20 {
+            let a = group.random_element(&mut rng);
+            let e = group.reduce(&a);
+            assert!(e.iter().all(|e| e.abs() <= 2));
+            assert!(e.iter().zip(group.bounds.iter()).all(|(e, bound)| e.abs() <= *bound));
+
+            // e - (a, 0, ..., 0) has to be an integer combination of the rows
+            let mut x: Vec<i128> = e.iter().map(|&e| e as i128).collect();
+            x[0] -= a.elements[0] as i128 + ((a.elements[1] as i128) << 64);
+            for j in 0..n {
+                let d = basis[j * n + j] as i128;
+                assert_eq!(x[j] % d, 0);
+                let k = x[j] / d;
+                if j + 1 < n {
+                    x[j + 1] -= k * basis[j * n + j + 1] as i128;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_invalid() {
+        let mut basis = [
+            2, -1, 0, 2,
+            2, 2, -2, 1,
+            2, 0, 1, -3,
+            1, 4, 3, 1,
+        ];
+        assert!(ClassGroup::<Toy>::new(&[183], &basis).is_ok());
+        assert_eq!(ClassGroup::<Toy>::new(&[185], &basis), Err(CsidhError::InvalidClassGroup));
+        assert_eq!(ClassGroup::<Toy>::new(&[183], &basis[..12]),
+                   Err(CsidhError::InvalidLength { expected: 16, actual: 12 }));
+
+        basis[0] = 4;
+        assert_eq!(ClassGroup::<Toy>::new(&[183], &basis), Err(CsidhError::InvalidClassGroup));
+
+        // The same lattice, but vectors reduced modulo this basis may not fit into exponents
+        let long = [
+            126, 123, -124, 64,
+            2, 2, -2, 1,
+            126, 124, -123, 59,
+            1, 4, 3, 1,
+        ];
+        assert_eq!(ClassGroup::<Toy>::new(&[183], &long), Err(CsidhError::InvalidClassGroup));
+    }
+
+    #[test]
+    fn check_arithmetic() {
+        let mut rng = thread_rng();
+        let mut basis = vec![0i8; 74 * 74];
+        for i in 0..74 {
+            basis[i * 74 + i] = 1;
+        }
+        basis[0] = 127;
+        let group = ClassGroup::<Csidh512>::new(&[127], &basis).unwrap();
+        assert_eq!(group.element_bytes(), 1);
+
+        let a = LargeUint::from_u64(100);
+        let b = LargeUint::from_u64(50);
+        assert_eq!(group.add(&a, &b), LargeUint::from_u64(23));
+        assert_eq!(group.sub(&b, &a), LargeUint::from_u64(77));
+        assert_eq!(group.mul(&a, &b), LargeUint::from_u64(5000 % 127));
+        assert_eq!(group.reduce(&a)[0], -27);
+
+        let group = ClassGroup::csidh512(&{
+            let mut basis = basis.clone();
+            basis[0] = 1;
+            basis
+        });
+        assert_eq!(group, Err(CsidhError::InvalidClassGroup));
+
+        let h = LargeUint::<Csidh512> { elements: CSIDH512_CLASS_NUMBER };
+        let group = ClassGroup::<Csidh512> {
+            class_number: h,
+            basis: Vec::new(),
+            inverse: Vec::new(),
+            bounds: [0; 74],
+        };
+        assert_eq!(group.element_bytes(), 33);
+        for _ in 0..100 {
+            let a = group.random_element(&mut rng);
+            let b = group.random_element(&mut rng);
+            assert!(a < h);
+            assert_eq!(group.sub(&group.add(&a, &b), &b), a);
+            assert_eq!(group.element_from_bytes(&group.element_to_bytes(&a)), Some(a));
+        }
+        assert_eq!(group.element_from_bytes(&group.element_to_bytes(&h)), None);
+        assert_eq!(group.element_from_bytes(&[0; 32]), None);
+    }
+}
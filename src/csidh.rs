@@ -1,43 +1,57 @@
+use core::convert::TryInto;
+
+use hkdf::Hkdf;
 use rand::prelude::*;
+use sha2::{Digest, Sha256};
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, Zeroizing};
 
-use crate::global;
+use crate::error::CsidhError;
+use crate::edwards;
 use crate::montgomery::{Curve, ProjectivePoint};
 use crate::galois::{LargeUint, GaloisElement};
+use crate::keyspace::KeySpace;
+use crate::params::{Array, Csidh512, CsidhParams};
+#[cfg(feature = "std")]
+use crate::sqrt_velu;
 
-fn action(curve: &Curve, private: &[i8]) -> LargeUint {
-    let mut rng = thread_rng();
-
-    let mut k = [LargeUint::from_u64(4) ;2];
+pub(crate) fn action<P: CsidhParams, R: CryptoRng + Rng>(curve: &Curve<GaloisElement<P>>,
+                                                         private: &P::Exponents,
+                                                         isogenies: IsogenyConfig, rng: &mut R)
+    -> Result<LargeUint<P>, CsidhError>
+{
+    // Everything derived from the key is wiped once the action is done
+    let mut k = Zeroizing::new([LargeUint::from_u64(4) ;2]);
 
-    let mut e = [[0u8; global::NUM_PRIMES]; 2];
+    let mut e = Zeroizing::new([P::Exponents::filled(0); 2]);
 
-    for i in 0..global::NUM_PRIMES {
+    for i in 0..P::NUM_PRIMES {
         let t = private[i];
 
         if t > 0 {
-            e[0][i] = t as u8;
+            e[0][i] = t;
             e[1][i] = 0;
-            k[1].mul_with_u64(global::PRIMES[i]);
+            k[1].mul_with_u64(P::PRIMES[i]);
         } else if t < 0 {
-            e[1][i] = -t as u8;
+            e[1][i] = -t;
             e[0][i] = 0;
-            k[0].mul_with_u64(global::PRIMES[i]);
+            k[0].mul_with_u64(P::PRIMES[i]);
         } else {
             e[0][i] = 0;
             e[1][i] = 0;
-            k[0].mul_with_u64(global::PRIMES[i]);
-            k[1].mul_with_u64(global::PRIMES[i]);
+            k[0].mul_with_u64(P::PRIMES[i]);
+            k[1].mul_with_u64(P::PRIMES[i]);
         }
     }
 
-    let mut p_curve = ProjectivePoint::new(curve.a, GaloisElement::from_u64(1));
+    let mut p_curve = Zeroizing::new(ProjectivePoint::new(curve.a, GaloisElement::from_u64(1)));
     let mut done = [false; 2];
 
     loop {
-        assert_eq!(p_curve.z, GaloisElement::from_u64(1));
-
-        let x = GaloisElement::random_element(&mut rng);
-        let sign = (!Curve::right_side(&p_curve.x, &x).is_square()) as usize;
+        let x = GaloisElement::random_element(rng);
+        let right_side = Curve::right_side(&p_curve.x, &x);
+        let square = if isogenies.vartime { right_side.is_square_vartime() } else { right_side.is_square() };
+        let sign = !square as usize;
 
         if done[sign] {
             continue;
@@ -45,72 +59,542 @@ fn action(curve: &Curve, private: &[i8]) -> LargeUint {
 
         let p = ProjectivePoint::new(x, GaloisElement::from_u64(1));
 
-        let mut p = p.ladder2(&p_curve, &k[sign]);
+        let mut p = Zeroizing::new(p.ladder2(&p_curve, &k[sign]));
+        let mut saved = Zeroizing::new([ProjectivePoint::new(GaloisElement::from_u64(1),
+                                                             GaloisElement::from_u64(0));
+                                        MAX_DEPTH]);
 
-        done[sign] = true;
+        evaluate_strategy(isogenies, &mut p_curve, &mut p, &mut saved, 0, &mut e[sign],
+                          &mut k[sign], 0, 0, P::NUM_PRIMES);
 
-        for i in (0..global::NUM_PRIMES).rev() {
-            if e[sign][i] != 0 {
-                let mut cof = LargeUint::from_u64(1);
-                for j in 0..i {
-                    if e[sign][j] != 0 {
-                        cof.mul_with_u64(global::PRIMES[j]);
-                    }
-                }
+        done[sign] = e[sign].as_ref().iter().all(|&e| e == 0);
+
+        if p_curve.z == GaloisElement::from_u64(0) {
+            return Err(CsidhError::DegenerateCurve);
+        }
+
+        if isogenies.vartime {
+            p_curve.normalize_vartime();
+        } else {
+            p_curve.normalize();
+        }
+
+        if done[1] && done[0] {
+            break;
+        }
+    }
+
+    return Ok(p_curve.x.into_large_uint());
+}
+
+/// The deepest a strategy may nest, i.e. the most points saved for later at any time
+const MAX_DEPTH: usize = 16;
+
+/// Does one isogeny for each prime of `lo..hi` with a nonzero exponent that the point has
+///
+/// `node` is the root of the part of `P::STRATEGY` covering these primes. Instead of multiplying
+/// the point by the product of the other primes once per prime, the primes are split in two
+/// according to the strategy. The point is saved and multiplied by one part, which gives a point
+/// for the other part. That part is handled first, pushing the saved point through its isogenies,
+/// after which the saved point is left with the first part only.
+#[allow(clippy::too_many_arguments)]
+fn evaluate_strategy<P: CsidhParams>(isogenies: IsogenyConfig,
+                                     curve: &mut ProjectivePoint<GaloisElement<P>>,
+                                     point: &mut ProjectivePoint<GaloisElement<P>>,
+                                     saved: &mut [ProjectivePoint<GaloisElement<P>>; MAX_DEPTH],
+                                     depth: usize, e: &mut P::Exponents, k: &mut LargeUint<P>,
+                                     node: usize, lo: usize, hi: usize)
+{
+    let active = |e: &P::Exponents, lo: usize, hi: usize| (lo..hi).any(|i| e[i] != 0);
+
+    if hi - lo == 1 {
+        if e[lo] != 0 && !point.is_infinity() {
+            isogenies.isogeny_slice(curve, &mut saved[..depth], point, P::PRIMES[lo]);
+            e[lo] -= 1;
+            if e[lo] == 0 {
+                k.mul_with_u64(P::PRIMES[lo]);
+            }
+        }
+        return;
+    }
+
+    let split = P::STRATEGY[node];
+    let mid = lo + split.unsigned_abs() as usize;
+    let left = (node + 1, lo, mid);
+    let right = (node + mid - lo, mid, hi);
+    let (first, second) = if split > 0 { (right, left) } else { (left, right) };
+
+    if !active(e, second.1, second.2) || !active(e, first.1, first.2) {
+        for (node, lo, hi) in [first, second] {
+            if active(e, lo, hi) {
+                evaluate_strategy(isogenies, curve, point, saved, depth, e, k, node, lo, hi);
+            }
+        }
+        return;
+    }
+
+    let mut cof = Zeroizing::new(LargeUint::from_u64(1));
+    for i in second.1..second.2 {
+        if e[i] != 0 {
+            cof.mul_with_u64(P::PRIMES[i]);
+        }
+    }
+
+    saved[depth] = *point;
+    let mut first_point = Zeroizing::new(point.ladder2(curve, &cof));
+    evaluate_strategy(isogenies, curve, &mut first_point, saved, depth + 1, e, k,
+                      first.0, first.1, first.2);
+
+    *point = saved[depth];
+    evaluate_strategy(isogenies, curve, point, saved, depth, e, k, second.0, second.1, second.2);
+}
+
+/// Samples a random point on the curve with coefficient `a`, or on its quadratic twist
+pub(crate) fn random_point<P: CsidhParams, R: CryptoRng + Rng>(rng: &mut R, a: &GaloisElement<P>, twist: bool)
+    -> ProjectivePoint<GaloisElement<P>>
+{
+    loop {
+        let x = GaloisElement::random_element(rng);
+        if Curve::right_side(a, &x).is_square() != twist {
+            return ProjectivePoint::new(x, GaloisElement::from_u64(1));
+        }
+    }
+}
+
+/// Evaluates the group action so that the work done does not depend on the private key
+///
+/// Every prime gets exactly `bounds[i]` isogenies, the ones the key does not need are dummy
+/// isogenies whose result is thrown away. As in Onuki et al. a point on the curve and one on its
+/// twist are pushed through every isogeny, the sign of the exponent only selects which of the two
+/// generates the kernel.
+pub(crate) fn action_constant_time<P: CsidhParams, R: CryptoRng + Rng>(curve: &Curve<GaloisElement<P>>,
+                                                                       private: &P::Exponents,
+                                                                       bounds: &P::Exponents,
+                                                                       isogenies: IsogenyConfig, rng: &mut R)
+    -> Result<LargeUint<P>, CsidhError>
+{
+    // Real isogenies left per prime, secret
+    let mut e = Zeroizing::new(P::Exponents::filled(0));
+    // Isogenies left per prime, real or dummy, these are public
+    let mut counts = *bounds;
+
+    for i in 0..P::NUM_PRIMES {
+        e[i] = private[i].abs();
+    }
+
+    let mut p_curve = Zeroizing::new(ProjectivePoint::new(curve.a, GaloisElement::from_u64(1)));
+
+    while counts.as_ref().iter().any(|&c| c != 0) {
+        let mut k = LargeUint::from_u64(4);
+        for i in 0..P::NUM_PRIMES {
+            if counts[i] == 0 {
+                k.mul_with_u64(P::PRIMES[i]);
+            }
+        }
+
+        // All scalars below only depend on the public counts, so their length may be used as the
+        // length of the ladder
+        let mut points = Zeroizing::new([
+            random_point(rng, &p_curve.x, false).ladder2_constant_time(&p_curve, &k, k.bits()),
+            random_point(rng, &p_curve.x, true).ladder2_constant_time(&p_curve, &k, k.bits()),
+        ]);
+
+        for i in (0..P::NUM_PRIMES).rev() {
+            if counts[i] == 0 {
+                continue;
+            }
+
+            // Move the point of the right sign to the front
+            let negative = private[i] < 0;
+            let [p0, p1] = &mut *points;
+            ProjectivePoint::conditional_swap(p0, p1, negative);
 
-                let kernel = p.ladder2(&p_curve, &cof);
-                if !kernel.is_infinity() {
-                    Curve::isogeny(&mut p_curve, &mut p, &kernel, global::PRIMES[i]);
-                    e[sign][i] -= 1;
-                    if e[sign][i] == 0 {
-                        k[sign].mul_with_u64(global::PRIMES[i]);
-                    }
+            let mut cof = LargeUint::from_u64(1);
+            for j in 0..i {
+                if counts[j] != 0 {
+                    cof.mul_with_u64(P::PRIMES[j]);
                 }
             }
 
-            done[sign] &= e[sign][i] == 0;
+            let l = LargeUint::from_u64(P::PRIMES[i]);
+            points[1] = points[1].ladder2_constant_time(&p_curve, &l, l.bits());
+
+            let kernel = Zeroizing::new(points[0].ladder2_constant_time(&p_curve, &cof, cof.bits()));
+            if !kernel.is_infinity() {
+                let mut new_curve = p_curve.clone();
+                let mut new_points = points.clone();
+                isogenies.isogeny_points(&mut new_curve, &mut new_points, &kernel, P::PRIMES[i]);
+
+                // A dummy isogeny keeps the curve and only removes l from the order of the point
+                let real = e[i] != 0;
+                points[0] = points[0].ladder2_constant_time(&p_curve, &l, l.bits());
+                p_curve.conditional_assign(&new_curve, real);
+                points[0].conditional_assign(&new_points[0], real);
+                points[1].conditional_assign(&new_points[1], real);
+
+                e[i] -= real as i8;
+                counts[i] -= 1;
+            }
+
+            let [p0, p1] = &mut *points;
+            ProjectivePoint::conditional_swap(p0, p1, negative);
+        }
+
+        if p_curve.z == GaloisElement::from_u64(0) {
+            return Err(CsidhError::DegenerateCurve);
         }
 
-        p_curve.z.inverse();
-        p_curve.x.mul_with(&p_curve.z);
-        p_curve.z = GaloisElement::from_u64(1);
+        p_curve.normalize();
+    }
 
-        if done[1] && done[0] {
-            break;
+    return Ok(p_curve.x.into_large_uint());
+}
+
+/// Learns the order of `point` at the primes `l_i` with `lower <= i < upper`, where `point` has
+/// already been multiplied by all the other factors of `p + 1`
+///
+/// The primes in the order of `point` get multiplied into `order`. Returns `Some(false)` if the
+/// order does not divide `p + 1` and `Some(true)` once the order is larger than `4 * sqrt(p)`.
+/// Splitting the range in halves shares most of the multiplications between the primes.
+fn validate_order<P: CsidhParams>(point: &ProjectivePoint<GaloisElement<P>>,
+                                  curve: &ProjectivePoint<GaloisElement<P>>, lower: usize,
+                                  upper: usize, order: &mut LargeUint<P>)
+    -> Option<bool>
+{
+    // We only learn something if the point is not the identity
+    if point.is_infinity() {
+        return None;
+    }
+
+    if upper - lower == 1 {
+        let l = LargeUint::from_u64(P::PRIMES[lower]);
+        if !point.ladder2(curve, &l).is_infinity() {
+            // The order of P does not divide p + 1
+            return Some(false);
+        }
+
+        order.mul_with_u64(P::PRIMES[lower]);
+
+        if *order > (LargeUint { elements: P::FOUR_SQRT_P }) {
+            return Some(true);
+        }
+        return None;
+    }
+
+    let mid = lower + (upper - lower).div_ceil(2);
+
+    let mut cu = LargeUint::from_u64(1);
+    let mut cl = LargeUint::from_u64(1);
+    for i in lower..mid {
+        cu.mul_with_u64(P::PRIMES[i]);
+    }
+    for i in mid..upper {
+        cl.mul_with_u64(P::PRIMES[i]);
+    }
+
+    // The larger primes tell more about the order, so they go first
+    let upper_point = point.ladder2(curve, &cu);
+    if let Some(valid) = validate_order(&upper_point, curve, mid, upper, order) {
+        return Some(valid);
+    }
+
+    let lower_point = point.ladder2(curve, &cl);
+    validate_order(&lower_point, curve, lower, mid, order)
+}
+
+/// Selects how a private key evaluates the group action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ActionMode {
+    /// The fast algorithm from the CSIDH paper, its running time depends on the key
+    #[default]
+    VariableTime,
+    /// Does the same number of isogenies for every key by adding dummy isogenies
+    ConstantTime,
+}
+
+/// Selects the curve model the isogenies of the group action are computed on
+///
+/// Both give the same results, keys and points stay in Montgomery form either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CurveModel {
+    /// The Montgomery formulas from the CSIDH paper
+    #[default]
+    Montgomery,
+    /// The twisted Edwards formulas of Moody and Shumow, which need fewer multiplications
+    Edwards,
+}
+
+/// The degree from which on the √élu formulas are used by default
+///
+/// √élu has to build its product trees and do an inversion for every isogeny. The `speed_degree_*`
+/// benchmarks compare it with Vélu's formulas, it breaks even between the degrees 180 and 300.
+const SQRT_VELU_THRESHOLD: u64 = 300;
+
+/// Selects the formulas for each isogeny of the group action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IsogenyConfig {
+    model: CurveModel,
+    sqrt_velu_threshold: u64,
+    vartime: bool,
+}
+
+impl Default for IsogenyConfig {
+    fn default() -> IsogenyConfig {
+        IsogenyConfig {
+            model: CurveModel::default(),
+            sqrt_velu_threshold: SQRT_VELU_THRESHOLD,
+            vartime: false,
+        }
+    }
+}
+
+impl IsogenyConfig {
+    /// The default formulas with variable time inversions and Legendre symbols, for actions
+    /// that only involve public values, like verifying a signature
+    #[cfg(feature = "std")]
+    pub(crate) fn public() -> IsogenyConfig {
+        IsogenyConfig { vartime: true, ..IsogenyConfig::default() }
+    }
+
+    fn isogeny_points<P: CsidhParams, const N: usize>(self, a: &mut ProjectivePoint<GaloisElement<P>>,
+                                                      points: &mut [ProjectivePoint<GaloisElement<P>>; N],
+                                                      k: &ProjectivePoint<GaloisElement<P>>, l: u64)
+    {
+        // √élu needs allocations, without `std` every isogeny uses Vélu's formulas
+        #[cfg(feature = "std")]
+        if l >= self.sqrt_velu_threshold {
+            return sqrt_velu::isogeny_points(a, points, k, l);
+        }
+
+        match self.model {
+            CurveModel::Montgomery => Curve::isogeny_points(a, points, k, l),
+            CurveModel::Edwards => edwards::isogeny_points(a, points, k, l),
         }
     }
 
-    return p_curve.x.into_large_uint();
+    /// Like `isogeny_points`, for any number of points
+    ///
+    /// The points are pushed through in chunks of up to four, only the first chunk updates `a`.
+    fn isogeny_slice<P: CsidhParams>(self, a: &mut ProjectivePoint<GaloisElement<P>>,
+                                     points: &mut [ProjectivePoint<GaloisElement<P>>],
+                                     k: &ProjectivePoint<GaloisElement<P>>, l: u64)
+    {
+        let domain = Zeroizing::new(*a);
+        if points.is_empty() {
+            return self.isogeny_points::<P, 0>(a, &mut [], k, l);
+        }
+
+        for (i, chunk) in points.chunks_mut(4).enumerate() {
+            let mut codomain = Zeroizing::new(*domain);
+            match chunk.len() {
+                1 => self.isogeny_points::<P, 1>(&mut codomain, chunk.try_into().unwrap(), k, l),
+                2 => self.isogeny_points::<P, 2>(&mut codomain, chunk.try_into().unwrap(), k, l),
+                3 => self.isogeny_points::<P, 3>(&mut codomain, chunk.try_into().unwrap(), k, l),
+                _ => self.isogeny_points::<P, 4>(&mut codomain, chunk.try_into().unwrap(), k, l),
+            }
+            if i == 0 {
+                *a = *codomain;
+            }
+        }
+    }
 }
 
-/// A private key for the CSIDH algorithm
-pub struct CsidhPrivateKey {
-    key: [i8; global::NUM_PRIMES]
+/// A private key for the CSIDH algorithm with the parameter set `P`
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// # use csidh::{Csidh1024, KeySpace, PrivateKey};
+/// # let mut rng: rand::rngs::ThreadRng = unimplemented!();
+/// let private = PrivateKey::<Csidh1024>::generate_new(&KeySpace::default(), &mut rng);
+/// let public = private.get_public_key();
+/// ```
+pub struct PrivateKey<P: CsidhParams> {
+    key: P::Exponents,
+    // The largest absolute exponent of each prime the key could have had, public
+    bounds: P::Exponents,
+    mode: ActionMode,
+    isogenies: IsogenyConfig,
 }
 
-impl CsidhPrivateKey {
-    /// Generates a new private key
+/// A private key for CSIDH-512
+pub type CsidhPrivateKey = PrivateKey<Csidh512>;
+
+impl<P: CsidhParams> PrivateKey<P> {
+    /// The largest absolute value an exponent of a private key may have
+    pub const MAX_EXPONENT: i8 = P::MAX_EXPONENT;
+
+    /// The default degree from which on isogenies are computed with the √élu formulas
+    pub const SQRT_VELU_THRESHOLD: u64 = SQRT_VELU_THRESHOLD;
+
+    /// Creates a private key from its exponent vector
+    ///
+    /// There has to be one exponent per prime, each between `-MAX_EXPONENT` and `MAX_EXPONENT`.
+    pub fn from_exponents(exponents: &[i8]) -> Result<PrivateKey<P>, CsidhError> {
+        PrivateKey::from_exponents_in(exponents, &KeySpace::default())
+    }
+
+    /// Creates a private key from its exponent vector, which has to lie in `key_space`
+    ///
+    /// The key is evaluated like one drawn from `key_space` with `generate_new`.
+    pub fn from_exponents_in(exponents: &[i8], key_space: &KeySpace<P>)
+        -> Result<PrivateKey<P>, CsidhError>
+    {
+        if exponents.len() != P::NUM_PRIMES {
+            return Err(CsidhError::InvalidLength {
+                expected: P::NUM_PRIMES,
+                actual: exponents.len(),
+            });
+        }
+
+        // Fill the key in place, so that it gets wiped on every exit
+        let mut private = PrivateKey::<P> {
+            key: P::Exponents::filled(0),
+            bounds: key_space.max_isogenies(),
+            mode: ActionMode::default(),
+            isogenies: IsogenyConfig::default(),
+        };
+
+        for (i, &e) in exponents.iter().enumerate() {
+            if !(key_space.lower()[i]..=key_space.upper()[i]).contains(&e) {
+                return Err(CsidhError::ExponentOutOfRange { index: i });
+            }
+            private.key[i] = e;
+        }
+
+        Ok(private)
+    }
+
+    /// Decodes a private key produced by `to_bytes`, its exponents have to lie in `key_space`
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use csidh::{CsidhPrivateKey, KeySpace};
+    /// let private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rand::thread_rng());
+    /// let decoded = CsidhPrivateKey::from_bytes(&private.to_bytes()[..], &KeySpace::default()).unwrap();
+    /// assert_eq!(decoded.get_public_key(), private.get_public_key());
+    /// ```
+    pub fn from_bytes(bytes: &[u8], key_space: &KeySpace<P>) -> Result<PrivateKey<P>, CsidhError> {
+        if bytes.len() != P::NUM_PRIMES {
+            return Err(CsidhError::InvalidLength {
+                expected: P::NUM_PRIMES,
+                actual: bytes.len(),
+            });
+        }
+
+        let mut exponents = Zeroizing::new(P::Exponents::filled(0));
+        for (e, &b) in exponents.as_mut().iter_mut().zip(bytes) {
+            *e = b as i8;
+        }
+        PrivateKey::from_exponents_in(exponents.as_ref(), key_space)
+    }
+
+    /// Decodes a private key in the packed format of the reference implementation, its
+    /// exponents have to lie in `key_space`
+    ///
+    /// See `to_packed_bytes` for the format.
+    pub fn from_packed_bytes(bytes: &[u8], key_space: &KeySpace<P>)
+        -> Result<PrivateKey<P>, CsidhError>
+    {
+        let expected = P::NUM_PRIMES.div_ceil(2);
+        if bytes.len() != expected {
+            return Err(CsidhError::InvalidLength {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+
+        // Sign extend the upper nibble for even and the lower nibble for odd indices
+        let mut exponents = Zeroizing::new(P::Exponents::filled(0));
+        for i in 0..P::NUM_PRIMES {
+            exponents[i] = ((bytes[i / 2] << (i % 2 * 4)) as i8) >> 4;
+        }
+        PrivateKey::from_exponents_in(exponents.as_ref(), key_space)
+    }
+
+    /// Generates a new private key from the given key space
     ///
     /// ## Example
     ///
     /// ```rust,no_run
-    /// # use csidh::CsidhPrivateKey;
+    /// # use csidh::{CsidhPrivateKey, KeySpace};
     /// # let mut rng: rand::rngs::ThreadRng = unimplemented!();
-    /// let private = CsidhPrivateKey::generate_new(&mut rng);
+    /// let private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
     /// let public = private.get_public_key();
     /// ```
-    pub fn generate_new<S: CryptoRng + Rng>(mut rng: &mut S) -> CsidhPrivateKey {
-        use rand::distributions::{Distribution, Uniform};
+    pub fn generate_new<S: CryptoRng + Rng>(key_space: &KeySpace<P>, rng: &mut S) -> PrivateKey<P> {
+        let mut private = PrivateKey::<P> {
+            key: P::Exponents::filled(0),
+            bounds: key_space.max_isogenies(),
+            mode: ActionMode::default(),
+            isogenies: IsogenyConfig::default(),
+        };
 
-        let between = Uniform::from(-5..=5);
-        let mut secret = [0i8; global::NUM_PRIMES];
+        key_space.sample(&mut private.key, rng);
 
-        for i in 0..global::NUM_PRIMES {
-            secret[i] = between.sample(&mut rng);
-        }
+        private
+    }
 
-        CsidhPrivateKey {
-            key: secret,
+    /// Selects how the group action is evaluated for this key
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use csidh::{ActionMode, CsidhPrivateKey, KeySpace};
+    /// # let mut rng: rand::rngs::ThreadRng = unimplemented!();
+    /// let private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng)
+    ///     .with_action_mode(ActionMode::ConstantTime);
+    /// let public = private.get_public_key();
+    /// ```
+    pub fn with_action_mode(mut self, mode: ActionMode) -> PrivateKey<P> {
+        self.mode = mode;
+        self
+    }
+
+    /// Selects the curve model the isogenies are computed on
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use csidh::{CsidhPrivateKey, CurveModel, KeySpace};
+    /// # let mut rng: rand::rngs::ThreadRng = unimplemented!();
+    /// let private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng)
+    ///     .with_curve_model(CurveModel::Edwards);
+    /// let public = private.get_public_key();
+    /// ```
+    pub fn with_curve_model(mut self, model: CurveModel) -> PrivateKey<P> {
+        self.isogenies.model = model;
+        self
+    }
+
+    /// Selects the degree from which on isogenies are computed with the √élu formulas
+    ///
+    /// The default is `SQRT_VELU_THRESHOLD`, `u64::MAX` turns √élu off. Without the `std`
+    /// feature √élu is not available and the threshold is ignored.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use csidh::{CsidhPrivateKey, KeySpace};
+    /// # let mut rng: rand::rngs::ThreadRng = unimplemented!();
+    /// let private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng)
+    ///     .with_sqrt_velu_threshold(100);
+    /// let public = private.get_public_key();
+    /// ```
+    pub fn with_sqrt_velu_threshold(mut self, threshold: u64) -> PrivateKey<P> {
+        self.isogenies.sqrt_velu_threshold = threshold;
+        self
+    }
+
+    fn action<R: CryptoRng + Rng>(&self, curve: &Curve<GaloisElement<P>>, rng: &mut R)
+        -> Result<LargeUint<P>, CsidhError>
+    {
+        match self.mode {
+            ActionMode::VariableTime => action(curve, &self.key, self.isogenies, rng),
+            ActionMode::ConstantTime => {
+                action_constant_time(curve, &self.key, &self.bounds, self.isogenies, rng)
+            }
         }
     }
 
@@ -119,30 +603,675 @@ impl CsidhPrivateKey {
     /// ## Example
     ///
     /// ```rust,no_run
-    /// # use csidh::CsidhPrivateKey;
+    /// # use csidh::{CsidhPrivateKey, KeySpace};
     /// # let mut rng: rand::rngs::ThreadRng = unimplemented!();
-    /// # let private = CsidhPrivateKey::generate_new(&mut rng);
+    /// # let private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
     /// let public = private.get_public_key();
     /// ```
-    pub fn get_public_key(&self) -> CsidhPublicKey {
+    #[cfg(feature = "std")]
+    pub fn get_public_key(&self) -> PublicKey<P> {
+        self.get_public_key_with_rng(&mut thread_rng())
+    }
+
+    /// Gets the associated public key, drawing the randomness for the group action from `rng`
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use csidh::{CsidhPrivateKey, KeySpace};
+    /// use rand::{SeedableRng, rngs::StdRng};
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
+    /// let public = private.get_public_key_with_rng(&mut rng);
+    /// ```
+    pub fn get_public_key_with_rng<R: CryptoRng + Rng>(&self, rng: &mut R) -> PublicKey<P> {
         let curve = Curve::new(0u32.into(), 1u32.into());
-        let a = action(&curve, &self.key);
+        let a = self.action(&curve, rng).expect("the starting curve is supersingular");
 
-        CsidhPublicKey {
+        PublicKey {
             a
         }
     }
 
     /// Computes the shared secret with another public key
-    pub fn get_shared_secret(&self, other: &CsidhPublicKey) -> Vec<u8> {
+    ///
+    /// The other key is validated first, an error is returned if it does not describe a
+    /// supersingular curve.
+    #[cfg(feature = "std")]
+    pub fn get_shared_secret(&self, other: &PublicKey<P>) -> Result<CsidhSharedSecret<P>, CsidhError> {
+        self.get_shared_secret_with_rng(other, &mut thread_rng())
+    }
+
+    /// Computes the shared secret with another public key, drawing the randomness for the
+    /// validation and the group action from `rng`
+    pub fn get_shared_secret_with_rng<R: CryptoRng + Rng>(&self, other: &PublicKey<P>, rng: &mut R)
+        -> Result<CsidhSharedSecret<P>, CsidhError>
+    {
+        if !other.validate_with_rng(rng) {
+            return Err(CsidhError::InvalidPublicKey);
+        }
+
         let their_curve = Curve::new(other.a, 1u32.into());
-        let s = action(&their_curve, &self.key);
-        s.as_bytes()
+        let s = Zeroizing::new(self.action(&their_curve, rng)?);
+        Ok(CsidhSharedSecret::new(&s))
    }
+
+    /// The exponent vector of the key, as accepted by `from_exponents`
+    pub fn exponents(&self) -> &P::Exponents {
+        &self.key
+    }
+
+    /// Encodes the key as its exponent vector, one byte in two's complement per prime
+    ///
+    /// The encoding is wiped from memory when it is dropped.
+    pub fn to_bytes(&self) -> Zeroizing<P::KeyBytes> {
+        let mut bytes = Zeroizing::new(P::KeyBytes::filled(0));
+        for (b, &e) in bytes.as_mut().iter_mut().zip(self.key.as_ref()) {
+            *b = e as u8;
+        }
+        bytes
+    }
+
+    /// Encodes the key in the packed format of the reference implementation
+    ///
+    /// Every byte holds two exponents as four bit two's complement numbers, the one of the even
+    /// prime index in the upper and the one of the odd index in the lower half. For CSIDH-512
+    /// this takes 37 bytes. Keys with an exponent outside of `-8..=7` cannot be packed.
+    pub fn to_packed_bytes(&self) -> Result<Zeroizing<P::PackedKeyBytes>, CsidhError> {
+        let mut bytes = Zeroizing::new(P::PackedKeyBytes::filled(0));
+        for (i, &e) in self.key.as_ref().iter().enumerate() {
+            if !(-8..=7).contains(&e) {
+                return Err(CsidhError::ExponentOutOfRange { index: i });
+            }
+            bytes[i / 2] |= (e as u8 & 0xf) << (4 - i % 2 * 4);
+        }
+        Ok(bytes)
+    }
+}
+
+impl<P: CsidhParams> Zeroize for PrivateKey<P> {
+    fn zeroize(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl<P: CsidhParams> Drop for PrivateKey<P> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// A shared secret computed with `PrivateKey::get_shared_secret`
+///
+/// The secret is the coefficient of the shared curve, which is far from uniformly random. Use
+/// `derive_key` to turn it into a symmetric key.
+///
+/// The secret is wiped from memory when it is dropped, is compared in constant time and does not
+/// show up in `Debug` output.
+#[derive(Clone)]
+pub struct CsidhSharedSecret<P: CsidhParams = Csidh512> {
+    pub(crate) bytes: P::Bytes,
+}
+
+const KEY_BYTES: usize = 32;
+
+impl<P: CsidhParams> CsidhSharedSecret<P> {
+    /// The length of a key returned by `derive_key` in bytes
+    pub const KEY_BYTES: usize = KEY_BYTES;
+
+    /// Encodes the coefficient of the shared curve like `PublicKey::to_bytes`
+    pub(crate) fn new(a: &LargeUint<P>) -> CsidhSharedSecret<P> {
+        let element = Zeroizing::new(GaloisElement::from_large_uint(*a));
+        let montgomery = Zeroizing::new(element.into_montgomery_uint());
+        CsidhSharedSecret {
+            bytes: montgomery.as_bytes(),
+        }
+    }
+
+    /// Derives a symmetric key from the secret, both public keys and `info`
+    ///
+    /// This is HKDF-SHA-256 with the shared curve coefficient as the input keying material, the
+    /// SHA-256 hash of the two public keys as the salt and `info` as the context. As the keys are
+    /// longer than a block of SHA-256, HMAC would hash them as well, so this is the same as using
+    /// the two keys as the salt directly. The public keys are ordered by their encoding, so both
+    /// parties arrive at the same key no matter which one they pass as `ours`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust,no_run
+    /// # use csidh::{CsidhPrivateKey, KeySpace};
+    /// let mut rng = rand::thread_rng();
+    /// let a_private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
+    /// let b_private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
+    /// let (a_public, b_public) = (a_private.get_public_key(), b_private.get_public_key());
+    ///
+    /// let a_key = a_private.get_shared_secret(&b_public).unwrap()
+    ///     .derive_key(&a_public, &b_public, b"example");
+    /// let b_key = b_private.get_shared_secret(&a_public).unwrap()
+    ///     .derive_key(&b_public, &a_public, b"example");
+    /// assert_eq!(a_key, b_key);
+    /// ```
+    pub fn derive_key(&self, ours: &PublicKey<P>, theirs: &PublicKey<P>, info: &[u8])
+        -> CsidhSharedKey
+    {
+        let (ours, theirs) = (ours.to_bytes(), theirs.to_bytes());
+        let (first, second) = if ours.as_ref() <= theirs.as_ref() {
+            (ours, theirs)
+        } else {
+            (theirs, ours)
+        };
+
+        let salt = Sha256::new()
+            .chain_update(first.as_ref())
+            .chain_update(second.as_ref())
+            .finalize();
+
+        let mut key = CsidhSharedKey {
+            bytes: [0; KEY_BYTES],
+        };
+        Hkdf::<Sha256>::new(Some(&salt), self.bytes.as_ref())
+            .expand(info, &mut key.bytes)
+            .expect("the key is shorter than 255 blocks");
+        key
+    }
+
+    /// The raw coefficient of the shared curve
+    ///
+    /// It is encoded like `PublicKey::to_bytes`, as little-endian bytes in Montgomery form, which
+    /// is the shared secret of the reference implementation. This is not uniformly random and must
+    /// not be used as a key directly, it is meant for interoperability with other implementations
+    /// and custom key derivation.
+    pub fn raw_coefficient_bytes(&self) -> &[u8] {
+        self.bytes.as_ref()
+    }
+}
+
+impl<P: CsidhParams> Drop for CsidhSharedSecret<P> {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+impl<P: CsidhParams> core::fmt::Debug for CsidhSharedSecret<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("CsidhSharedSecret").finish_non_exhaustive()
+    }
 }
 
-/// A public key for the CSIDH algorithm
-pub struct CsidhPublicKey {
-    a: LargeUint,
+impl<P: CsidhParams> ConstantTimeEq for CsidhSharedSecret<P> {
+    fn ct_eq(&self, other: &CsidhSharedSecret<P>) -> Choice {
+        self.bytes.as_ref().ct_eq(other.bytes.as_ref())
+    }
+}
+
+impl<P: CsidhParams> PartialEq for CsidhSharedSecret<P> {
+    fn eq(&self, other: &CsidhSharedSecret<P>) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl<P: CsidhParams> Eq for CsidhSharedSecret<P> {}
+
+/// A symmetric key derived with `CsidhSharedSecret::derive_key`
+///
+/// Like the secret, the key is wiped from memory when it is dropped, is compared in constant time
+/// and does not show up in `Debug` output.
+#[derive(Clone)]
+pub struct CsidhSharedKey {
+    bytes: [u8; KEY_BYTES],
+}
+
+impl CsidhSharedKey {
+    /// The key as bytes
+    pub fn as_bytes(&self) -> &[u8; KEY_BYTES] {
+        &self.bytes
+    }
+}
+
+impl Drop for CsidhSharedKey {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+impl core::fmt::Debug for CsidhSharedKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("CsidhSharedKey").finish_non_exhaustive()
+    }
+}
+
+impl ConstantTimeEq for CsidhSharedKey {
+    fn ct_eq(&self, other: &CsidhSharedKey) -> Choice {
+        self.bytes.ct_eq(&other.bytes)
+    }
+}
+
+impl PartialEq for CsidhSharedKey {
+    fn eq(&self, other: &CsidhSharedKey) -> bool {
+        self.ct_eq(other).into()
+    }
 }
 
+impl Eq for CsidhSharedKey {}
+
+/// A public key for the CSIDH algorithm with the parameter set `P`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PublicKey<P: CsidhParams> {
+    pub(crate) a: LargeUint<P>,
+}
+
+/// A public key for CSIDH-512
+pub type CsidhPublicKey = PublicKey<Csidh512>;
+
+impl<P: CsidhParams> PublicKey<P> {
+    /// The length of an encoded public key in bytes
+    pub const BYTES: usize = <P::Bytes as Array<u8>>::LEN;
+
+    /// Encodes the public key
+    ///
+    /// The curve coefficient is written in Montgomery form as little-endian 64 bit limbs, which
+    /// is the same layout the reference C implementation uses for its `public_key`.
+    pub fn to_bytes(&self) -> P::Bytes {
+        GaloisElement::from_large_uint(self.a).into_montgomery_uint().as_bytes()
+    }
+
+    /// Checks whether this key describes a supersingular curve
+    ///
+    /// This samples random points and checks that their order divides `p + 1`, once a point of
+    /// order larger than `4 * sqrt(p)` is found the curve has to be supersingular. Invalid keys
+    /// must never be used in `get_shared_secret`, as the group action is not defined for them.
+    #[cfg(feature = "std")]
+    pub fn validate(&self) -> bool {
+        self.validate_with_rng(&mut thread_rng())
+    }
+
+    /// Like `validate`, but draws the random points from `rng`
+    pub fn validate_with_rng<R: CryptoRng + Rng>(&self, rng: &mut R) -> bool {
+        let a = GaloisElement::from_large_uint(self.a);
+        let two = GaloisElement::from_u64(2);
+
+        // A = +-2 gives a singular curve
+        if a == two || a == GaloisElement::from_u64(0) - two {
+            return false;
+        }
+
+        let curve = ProjectivePoint::new(a, GaloisElement::from_u64(1));
+
+        loop {
+            let x = GaloisElement::random_element(rng);
+            let point = ProjectivePoint::new(x, GaloisElement::from_u64(1));
+
+            // Remove the maximal power of two in p + 1
+            let point = point.double2(&curve).double2(&curve);
+
+            let mut order = LargeUint::from_u64(1);
+            if let Some(valid) = validate_order(&point, &curve, 0, P::NUM_PRIMES, &mut order) {
+                return valid;
+            }
+        }
+    }
+
+    /// Decodes a public key produced by `to_bytes`
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use csidh::CsidhPublicKey;
+    /// let bytes = [0u8; CsidhPublicKey::BYTES];
+    /// let public = CsidhPublicKey::from_bytes(&bytes).unwrap();
+    /// assert_eq!(public.to_bytes(), bytes);
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<PublicKey<P>, CsidhError> {
+        if bytes.len() != PublicKey::<P>::BYTES {
+            return Err(CsidhError::InvalidLength {
+                expected: PublicKey::<P>::BYTES,
+                actual: bytes.len(),
+            });
+        }
+
+        let a = LargeUint::from_bytes(bytes);
+        if a >= (LargeUint { elements: P::P }) {
+            return Err(CsidhError::OutOfRange);
+        }
+
+        Ok(PublicKey {
+            a: GaloisElement::from_montgomery_uint(a).into_large_uint(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::{Csidh1024, Csidh1792};
+
+    #[test]
+    fn check_public_key_bytes() {
+        let public = CsidhPublicKey { a: LargeUint::from_u64(1) };
+        let bytes = public.to_bytes();
+        assert_eq!(&bytes[..], &GaloisElement::<Csidh512>::one().into_montgomery_uint().as_bytes()[..]);
+        assert_eq!(CsidhPublicKey::from_bytes(&bytes), Ok(public));
+
+        let public = CsidhPublicKey { a: LargeUint { elements: Csidh512::P_MINUS_2 } };
+        assert_eq!(CsidhPublicKey::from_bytes(&public.to_bytes()), Ok(public));
+    }
+
+    #[test]
+    fn check_derive_key() {
+        let secret = CsidhSharedSecret::<Csidh512> { bytes: [7; 64] };
+        let ours = CsidhPublicKey { a: LargeUint::from_u64(1) };
+        let theirs = CsidhPublicKey { a: LargeUint::from_u64(2) };
+
+        let key = secret.derive_key(&ours, &theirs, b"info");
+        assert_eq!(key, secret.derive_key(&theirs, &ours, b"info"));
+        assert_ne!(key, secret.derive_key(&ours, &theirs, b"other info"));
+        assert_ne!(key, secret.derive_key(&ours, &ours, b"info"));
+        assert_ne!(&key.as_bytes()[..], &secret.raw_coefficient_bytes()[..32]);
+
+        let other = CsidhSharedSecret::<Csidh512> { bytes: [8; 64] };
+        assert_ne!(key, other.derive_key(&ours, &theirs, b"info"));
+
+        assert_eq!(format!("{:?}", secret), "CsidhSharedSecret { .. }");
+        assert_eq!(format!("{:?}", key), "CsidhSharedKey { .. }");
+    }
+
+    #[test]
+    fn check_shared_secret_bytes() {
+        // The shared curve of two keys is the public key of the sum of their exponents
+        let mut exponents = [0i8; Csidh512::NUM_PRIMES];
+        exponents[0] = 1;
+        let alice = CsidhPrivateKey::from_exponents(&exponents).unwrap();
+        exponents[0] = 0;
+        exponents[1] = -1;
+        let bob = CsidhPrivateKey::from_exponents(&exponents).unwrap();
+        exponents[0] = 1;
+        let both = CsidhPrivateKey::from_exponents(&exponents).unwrap();
+
+        let secret = alice.get_shared_secret(&bob.get_public_key()).unwrap();
+        assert_eq!(secret.raw_coefficient_bytes(), &both.get_public_key().to_bytes()[..]);
+    }
+
+    #[test]
+    fn check_from_exponents() {
+        let mut exponents = [0i8; Csidh512::NUM_PRIMES];
+        exponents[3] = -5;
+        exponents[73] = 5;
+        let private = CsidhPrivateKey::from_exponents(&exponents).unwrap();
+        assert_eq!(private.key, exponents);
+
+        assert_eq!(CsidhPrivateKey::from_exponents(&exponents[1..]).err(),
+                   Some(CsidhError::InvalidLength { expected: 74, actual: 73 }));
+
+        exponents[10] = 6;
+        assert_eq!(CsidhPrivateKey::from_exponents(&exponents).err(),
+                   Some(CsidhError::ExponentOutOfRange { index: 10 }));
+    }
+
+    #[test]
+    fn check_private_key_bytes() {
+        let mut exponents = [0i8; Csidh512::NUM_PRIMES];
+        exponents[0] = -5;
+        exponents[1] = 3;
+        exponents[72] = -1;
+        exponents[73] = 5;
+        let private = CsidhPrivateKey::from_exponents(&exponents).unwrap();
+        let key_space = KeySpace::default();
+
+        let bytes = private.to_bytes();
+        assert_eq!((bytes.len(), bytes[0], bytes[1], bytes[72]), (74, 0xfb, 3, 0xff));
+        assert_eq!(CsidhPrivateKey::from_bytes(&bytes[..], &key_space).unwrap().key, exponents);
+
+        let packed = private.to_packed_bytes().unwrap();
+        assert_eq!(packed.len(), 37);
+        assert_eq!((packed[0], packed[1], packed[36]), (0xb3, 0, 0xf5));
+        assert_eq!(CsidhPrivateKey::from_packed_bytes(&packed[..], &key_space).unwrap().key, exponents);
+
+        assert_eq!(CsidhPrivateKey::from_bytes(&bytes[1..], &key_space).err(),
+                   Some(CsidhError::InvalidLength { expected: 74, actual: 73 }));
+        assert_eq!(CsidhPrivateKey::from_packed_bytes(&packed[1..], &key_space).err(),
+                   Some(CsidhError::InvalidLength { expected: 37, actual: 36 }));
+        assert_eq!(CsidhPrivateKey::from_bytes(&bytes[..], &KeySpace::ternary()).err(),
+                   Some(CsidhError::ExponentOutOfRange { index: 0 }));
+        assert_eq!(CsidhPrivateKey::from_packed_bytes(&packed[..], &KeySpace::nonnegative(5).unwrap()).err(),
+                   Some(CsidhError::ExponentOutOfRange { index: 0 }));
+
+        // Wider key spaces are fine as long as the exponents fit into a nibble
+        let wide = KeySpace::uniform(9).unwrap();
+        exponents[5] = -8;
+        let private = CsidhPrivateKey::from_exponents_in(&exponents, &wide).unwrap();
+        assert_eq!(private.to_packed_bytes().unwrap()[2], 0x08);
+        exponents[5] = 8;
+        let private = CsidhPrivateKey::from_exponents_in(&exponents, &wide).unwrap();
+        assert_eq!(private.to_packed_bytes().err(),
+                   Some(CsidhError::ExponentOutOfRange { index: 5 }));
+        assert_eq!(CsidhPrivateKey::from_bytes(&private.to_bytes()[..], &wide).unwrap().key, exponents);
+    }
+
+    fn velu(model: CurveModel) -> IsogenyConfig {
+        IsogenyConfig {
+            model,
+            sqrt_velu_threshold: u64::MAX,
+            vartime: false,
+        }
+    }
+
+    #[test]
+    fn check_constant_time_action() {
+        let mut exponents = [0i8; Csidh512::NUM_PRIMES];
+        exponents[0] = 2;
+        exponents[1] = -1;
+        exponents[73] = -1;
+        // More isogenies than needed for the first prime, some of them are dummies
+        let mut bounds = [1i8; Csidh512::NUM_PRIMES];
+        bounds[0] = 3;
+        let curve = Curve::<GaloisElement<Csidh512>>::new(0u32.into(), 1u32.into());
+
+        let mut rng = thread_rng();
+        let fast = action(&curve, &exponents, velu(CurveModel::Montgomery), &mut rng).unwrap();
+        let constant = action_constant_time(&curve, &exponents, &bounds,
+                                            velu(CurveModel::Montgomery), &mut rng)
+            .unwrap();
+        assert_eq!(fast, constant);
+    }
+
+    #[test]
+    fn check_edwards_action() {
+        let mut exponents = [0i8; Csidh512::NUM_PRIMES];
+        exponents[0] = -2;
+        exponents[40] = 1;
+        exponents[73] = 1;
+        let mut bounds = [1i8; Csidh512::NUM_PRIMES];
+        bounds[0] = 2;
+        let curve = Curve::<GaloisElement<Csidh512>>::new(0u32.into(), 1u32.into());
+
+        let mut rng = thread_rng();
+        let montgomery = action(&curve, &exponents, velu(CurveModel::Montgomery), &mut rng).unwrap();
+        let edwards = action(&curve, &exponents, velu(CurveModel::Edwards), &mut rng).unwrap();
+        let constant = action_constant_time(&curve, &exponents, &bounds, velu(CurveModel::Edwards),
+                                            &mut rng)
+            .unwrap();
+        assert_eq!(montgomery, edwards);
+        assert_eq!(montgomery, constant);
+    }
+
+    /// The largest number of points `evaluate_strategy` saves at once with `strategy`
+    fn strategy_depth(strategy: &[i16], node: usize, lo: usize, hi: usize) -> usize {
+        if hi - lo == 1 {
+            return 0;
+        }
+
+        let mid = lo + strategy[node].unsigned_abs() as usize;
+        let left = strategy_depth(strategy, node + 1, lo, mid);
+        let right = strategy_depth(strategy, node + mid - lo, mid, hi);
+        if strategy[node] > 0 {
+            (right + 1).max(left)
+        } else {
+            (left + 1).max(right)
+        }
+    }
+
+    #[test]
+    fn check_strategy_depth() {
+        assert!(strategy_depth(Csidh512::STRATEGY, 0, 0, Csidh512::NUM_PRIMES) <= MAX_DEPTH);
+        assert!(strategy_depth(Csidh1024::STRATEGY, 0, 0, Csidh1024::NUM_PRIMES) <= MAX_DEPTH);
+        assert!(strategy_depth(Csidh1792::STRATEGY, 0, 0, Csidh1792::NUM_PRIMES) <= MAX_DEPTH);
+    }
+
+    #[test]
+    fn check_sqrt_velu_action() {
+        let mut exponents = [0i8; Csidh512::NUM_PRIMES];
+        exponents[0] = 1;
+        exponents[60] = -1;
+        exponents[72] = 1;
+        exponents[73] = -2;
+        let mut bounds = [1i8; Csidh512::NUM_PRIMES];
+        bounds[73] = 2;
+        let curve = Curve::<GaloisElement<Csidh512>>::new(0u32.into(), 1u32.into());
+        let everywhere = IsogenyConfig {
+            model: CurveModel::Montgomery,
+            sqrt_velu_threshold: 3,
+            vartime: false,
+        };
+
+        let mut rng = thread_rng();
+        let velu = action(&curve, &exponents, velu(CurveModel::Montgomery), &mut rng).unwrap();
+        let sqrt_velu = action(&curve, &exponents, everywhere, &mut rng).unwrap();
+        let constant = action_constant_time(&curve, &exponents, &bounds, IsogenyConfig::default(),
+                                            &mut rng)
+            .unwrap();
+        assert_eq!(velu, sqrt_velu);
+        assert_eq!(velu, constant);
+    }
+
+    #[test]
+    fn check_key_space() {
+        let mut rng = thread_rng();
+
+        // Weighted towards the small primes, which are cheap, and nonnegative for some
+        let mut lower = [0i8; Csidh512::NUM_PRIMES];
+        let mut upper = [0i8; Csidh512::NUM_PRIMES];
+        for i in 0..8 {
+            lower[i] = if i % 2 == 0 { -2 } else { 0 };
+            upper[i] = 8 / (i as i8 + 2);
+        }
+        let key_space = KeySpace::from_ranges(&lower, &upper).unwrap();
+
+        let private = CsidhPrivateKey::generate_new(&key_space, &mut rng);
+        assert!(key_space.contains(&private.key));
+        assert_eq!(private.bounds[0], 4);
+        assert_eq!(private.bounds[73], 0);
+
+        let constant = CsidhPrivateKey::from_exponents(&private.key).unwrap();
+        let constant = PrivateKey { bounds: private.bounds, ..constant }
+            .with_action_mode(ActionMode::ConstantTime);
+        assert_eq!(private.get_public_key(), constant.get_public_key());
+    }
+
+    #[test]
+    fn check_seeded_rng() {
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
+        let public = private.get_public_key_with_rng(&mut rng);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let again = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
+        assert_eq!(private.key, again.key);
+        assert_eq!(public, again.get_public_key_with_rng(&mut rng));
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let other = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
+        let other_public = other.get_public_key_with_rng(&mut rng);
+
+        assert_eq!(private.get_shared_secret_with_rng(&other_public, &mut rng),
+                   other.get_shared_secret_with_rng(&public, &mut rng));
+    }
+
+    #[test]
+    fn check_zeroize() {
+        let mut exponents = [1i8; Csidh512::NUM_PRIMES];
+        exponents[5] = -3;
+        let mut private = CsidhPrivateKey::from_exponents(&exponents).unwrap();
+
+        // Drop wipes the key through this
+        private.zeroize();
+        assert_eq!(private.key, [0; Csidh512::NUM_PRIMES]);
+    }
+
+    #[test]
+    fn check_validate() {
+        let base = CsidhPublicKey { a: LargeUint::from_u64(0) };
+        assert!(base.validate());
+
+        let two = CsidhPublicKey { a: LargeUint::from_u64(2) };
+        assert!(!two.validate());
+
+        let minus_two = CsidhPublicKey {
+            a: (GaloisElement::from_u64(0) - GaloisElement::from_u64(2)).into_large_uint()
+        };
+        assert!(!minus_two.validate());
+
+        let ordinary = CsidhPublicKey { a: LargeUint::from_u64(1) };
+        assert!(!ordinary.validate());
+
+        let public = CsidhPublicKey {
+            a: LargeUint {
+                elements: [
+                    0x4d92b18ec55cac60, 0x9f0c63cf38ab2095, 0x45c6693c36a28f00, 0xacf35959965a0ae3,
+                    0x1797da6302148e0b, 0xf47be92a5f836ae6, 0xd3aff080771bf39a, 0x0f25094e8e013184,
+                ]
+            }
+        };
+        assert!(public.validate());
+    }
+
+    #[test]
+    fn check_public_key_rejects() {
+        assert_eq!(CsidhPublicKey::from_bytes(&[0; 63]),
+                   Err(CsidhError::InvalidLength { expected: 64, actual: 63 }));
+        assert_eq!(CsidhPublicKey::from_bytes(&LargeUint::<Csidh512> { elements: Csidh512::P }.as_bytes()),
+                   Err(CsidhError::OutOfRange));
+        assert_eq!(CsidhPublicKey::from_bytes(&[0xff; 64]),
+                   Err(CsidhError::OutOfRange));
+    }
+
+    fn check_larger_params<P: CsidhParams>() {
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let base = PublicKey::<P> { a: LargeUint::from_u64(0) };
+        assert!(base.validate_with_rng(&mut rng));
+        let ordinary = PublicKey::<P> { a: LargeUint::from_u64(1) };
+        assert!(!ordinary.validate_with_rng(&mut rng));
+
+        // Only a few isogenies each, the full action is slow for the larger primes
+        let mut exponents = P::Exponents::filled(0);
+        exponents[0] = 1;
+        exponents[P::NUM_PRIMES - 1] = -1;
+        let alice = PrivateKey::<P>::from_exponents(exponents.as_ref()).unwrap();
+        let mut exponents = P::Exponents::filled(0);
+        exponents[1] = -1;
+        exponents[P::NUM_PRIMES - 2] = 1;
+        let bob = PrivateKey::<P>::from_exponents(exponents.as_ref()).unwrap();
+
+        let alice_public = alice.get_public_key_with_rng(&mut rng);
+        let bob_public = bob.get_public_key_with_rng(&mut rng);
+        assert_eq!(PublicKey::<P>::from_bytes(alice_public.to_bytes().as_ref()), Ok(alice_public));
+
+        assert_eq!(alice.get_shared_secret_with_rng(&bob_public, &mut rng).unwrap(),
+                   bob.get_shared_secret_with_rng(&alice_public, &mut rng).unwrap());
+    }
+
+    #[test]
+    fn check_csidh_1024() {
+        check_larger_params::<Csidh1024>();
+    }
+
+    #[test]
+    fn check_csidh_1792() {
+        check_larger_params::<Csidh1792>();
+    }
+}
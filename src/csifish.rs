@@ -0,0 +1,412 @@
+//! CSI-FiSh, the signature scheme of Beullens, Kleinjung and Vercauteren
+//!
+//! The signer knows `a_1, ..., a_n` modulo the class number with public curves `E_j = [a_j] E_0`,
+//! and `E_-j`, the quadratic twist of `E_j`, is `[-a_j] E_0`. Each round commits to `[b] E_0` for
+//! a random `b`, and answers the challenge `c` in `-n..=n` with `r = b - a_c`, which reveals
+//! nothing as `b` is uniform modulo the class number. The verifier checks `[r] E_c = [b] E_0`.
+//! Fiat–Shamir turns this into a signature, the challenges are derived from a SHA-256 hash of the
+//! commitments and the message.
+
+use rand::prelude::*;
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::classgroup::ClassGroup;
+use crate::csidh::{action, action_constant_time, IsogenyConfig, PublicKey};
+use crate::error::CsidhError;
+use crate::galois::{GaloisElement, LargeUint};
+use crate::montgomery::Curve;
+use crate::params::{Csidh512, CsidhParams};
+
+/// The length of the hash at the start of a signature
+pub(crate) const HASH_BYTES: usize = 32;
+
+/// CSI-FiSh signatures over the class group of the parameter set `P`
+///
+/// A public key has `curves` curves, and a signature answers `rounds` challenges, each one of
+/// `2 * curves + 1` possibilities. More curves make signatures shorter and faster to produce and
+/// verify, at the cost of larger public keys.
+///
+/// Key generation and signing evaluate the group action in constant time, verifying only handles
+/// public data and does so in variable time.
+///
+/// ## Example
+///
+/// The reduced basis of CSIDH-512 is not part of the crate, here it is read from a file with its
+/// `74 * 74` exponents as bytes in two's complement.
+///
+/// ```rust,no_run
+/// # use csidh::{ClassGroup, CsiFish};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let basis: Vec<i8> = std::fs::read("csidh512-basis")?.into_iter().map(|b| b as i8).collect();
+/// let scheme = CsiFish::with_class_group(ClassGroup::csidh512(&basis)?);
+/// let (private, public) = scheme.generate_keys(&mut rand::thread_rng());
+///
+/// let signature = scheme.sign(&private, b"message")?;
+/// assert!(scheme.verify(&public, b"message", &signature));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsiFish<P: CsidhParams = Csidh512> {
+    class_group: ClassGroup<P>,
+    curves: usize,
+    rounds: usize,
+}
+
+impl<P: CsidhParams> CsiFish<P> {
+    /// The number of curves of a public key by default
+    pub const DEFAULT_CURVES: usize = 15;
+
+    /// The number of rounds by default, enough for `2^128` challenges with `DEFAULT_CURVES`
+    pub const DEFAULT_ROUNDS: usize = 26;
+
+    /// CSI-FiSh with the default number of curves and rounds
+    pub fn with_class_group(class_group: ClassGroup<P>) -> CsiFish<P> {
+        CsiFish {
+            class_group,
+            curves: Self::DEFAULT_CURVES,
+            rounds: Self::DEFAULT_ROUNDS,
+        }
+    }
+
+    /// CSI-FiSh with `curves` public curves and `rounds` challenges per signature
+    ///
+    /// There have to be between one and `32767` curves and at least one round. The security
+    /// level is `rounds * log2(2 * curves + 1)` bits.
+    pub fn new(class_group: ClassGroup<P>, curves: usize, rounds: usize)
+        -> Result<CsiFish<P>, CsidhError>
+    {
+        if !(1..=i16::MAX as usize).contains(&curves) || rounds == 0 {
+            return Err(CsidhError::InvalidParameters);
+        }
+
+        Ok(CsiFish {
+            class_group,
+            curves,
+            rounds,
+        })
+    }
+
+    /// The length of a signature in bytes
+    pub fn signature_bytes(&self) -> usize {
+        HASH_BYTES + self.rounds * self.class_group.element_bytes()
+    }
+
+    /// Generates a new key pair
+    pub fn generate_keys<R: CryptoRng + Rng>(&self, rng: &mut R)
+        -> (CsiFishPrivateKey<P>, CsiFishPublicKey<P>)
+    {
+        let private = CsiFishPrivateKey {
+            keys: (0..self.curves).map(|_| self.class_group.random_element(rng)).collect(),
+        };
+
+        let curves = private.keys.iter()
+            .map(|a| PublicKey {
+                a: self.act_secret(a, rng)
+                    .expect("the starting curve is supersingular"),
+            })
+            .collect();
+
+        (private, CsiFishPublicKey { curves })
+    }
+
+    /// Signs `message`
+    ///
+    /// Fails with `CsidhError::InvalidParameters` if the key was generated with a different number
+    /// of curves.
+    pub fn sign(&self, private: &CsiFishPrivateKey<P>, message: &[u8])
+        -> Result<CsiFishSignature, CsidhError>
+    {
+        self.sign_with_rng(private, message, &mut thread_rng())
+    }
+
+    /// Signs `message`, drawing the commitments and the randomness for the group action from
+    /// `rng`
+    pub fn sign_with_rng<R: CryptoRng + Rng>(&self, private: &CsiFishPrivateKey<P>,
+                                             message: &[u8], rng: &mut R)
+        -> Result<CsiFishSignature, CsidhError>
+    {
+        if private.keys.len() != self.curves {
+            return Err(CsidhError::InvalidParameters);
+        }
+
+        let commitments: Zeroizing<Vec<LargeUint<P>>> = Zeroizing::new(
+            (0..self.rounds).map(|_| self.class_group.random_element(rng)).collect()
+        );
+
+        let curves: Vec<LargeUint<P>> = commitments.iter()
+            .map(|b| {
+                self.act_secret(b, rng)
+                    .expect("the starting curve is supersingular")
+            })
+            .collect();
+
+        let hash = hash(&curves, message);
+
+        let mut bytes = hash.to_vec();
+        for (b, c) in commitments.iter().zip(challenges(&hash, self.curves, self.rounds)) {
+            let zero = LargeUint::from_u64(0);
+            let a = match c {
+                0 => zero,
+                c if c > 0 => private.keys[c as usize - 1],
+                c => self.class_group.sub(&zero, &private.keys[(-c) as usize - 1]),
+            };
+
+            let r = Zeroizing::new(self.class_group.sub(b, &a));
+            bytes.extend_from_slice(&self.class_group.element_to_bytes(&r));
+        }
+
+        Ok(CsiFishSignature {
+            bytes,
+        })
+    }
+
+    /// Checks that `signature` is a valid signature of `message` under `public`
+    ///
+    /// Public keys that do not consist of supersingular curves never verify a signature.
+    pub fn verify(&self, public: &CsiFishPublicKey<P>, message: &[u8],
+                  signature: &CsiFishSignature) -> bool
+    {
+        self.verify_with_rng(public, message, signature, &mut thread_rng())
+    }
+
+    /// Like `verify`, but draws the randomness for the validation and the group action from `rng`
+    pub fn verify_with_rng<R: CryptoRng + Rng>(&self, public: &CsiFishPublicKey<P>,
+                                               message: &[u8], signature: &CsiFishSignature,
+                                               rng: &mut R) -> bool
+    {
+        let bytes = signature.as_bytes();
+        if public.curves.len() != self.curves || bytes.len() != self.signature_bytes() {
+            return false;
+        }
+
+        if !public.curves.iter().all(|curve| curve.validate_with_rng(rng)) {
+            return false;
+        }
+
+        let (expected, responses) = bytes.split_at(HASH_BYTES);
+        let mut curves = Vec::with_capacity(self.rounds);
+        for (r, c) in responses.chunks(self.class_group.element_bytes()).zip(challenges(expected, self.curves, self.rounds)) {
+            let r = match self.class_group.element_from_bytes(r) {
+                Some(r) => r,
+                None => return false,
+            };
+
+            match self.act(&challenge_curve(&public.curves, c), &r, rng) {
+                Ok(curve) => curves.push(curve),
+                Err(_) => return false,
+            }
+        }
+
+        hash(&curves, message)[..] == *expected
+    }
+
+    /// Evaluates the class of `l_1^e` on the curve with coefficient `a`, for public `e`
+    fn act<R: CryptoRng + Rng>(&self, a: &LargeUint<P>, e: &LargeUint<P>, rng: &mut R)
+        -> Result<LargeUint<P>, CsidhError>
+    {
+        let exponents = self.class_group.reduce(e);
+        action(&Curve::new(*a, 1u32.into()), &exponents, IsogenyConfig::public(), rng)
+    }
+
+    /// Evaluates the class of `l_1^e` on `E_0` for secret `e`
+    ///
+    /// Keys and commitments are secret, and the responses published with a signature are their
+    /// differences. The action does the number of isogenies the reduced basis allows for each
+    /// prime, no matter the exponents.
+    fn act_secret<R: CryptoRng + Rng>(&self, e: &LargeUint<P>, rng: &mut R)
+        -> Result<LargeUint<P>, CsidhError>
+    {
+        let exponents = Zeroizing::new(self.class_group.reduce(e));
+        action_constant_time(&Curve::new(LargeUint::from_u64(0), 1u32.into()), &exponents,
+                             self.class_group.bounds(), IsogenyConfig::default(), rng)
+    }
+}
+
+/// The hash of the committed curves and the message
+pub(crate) fn hash<P: CsidhParams>(curves: &[LargeUint<P>], message: &[u8]) -> [u8; HASH_BYTES] {
+    let mut hasher = Sha256::new();
+    for &a in curves {
+        hasher.update(PublicKey { a }.to_bytes());
+    }
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// `rounds` challenges in `-curves..=curves` derived from `hash`
+///
+/// The hash is expanded by hashing it with a counter, every two bytes give a candidate that is
+/// kept if it is small enough after masking.
+pub(crate) fn challenges(hash: &[u8], curves: usize, rounds: usize) -> Vec<i32> {
+    let count = 2 * curves as u32 + 1;
+    let mask = count.next_power_of_two() - 1;
+
+    let mut challenges = Vec::with_capacity(rounds);
+    for counter in 0u32.. {
+        let block = Sha256::new().chain_update(hash).chain_update(counter.to_le_bytes()).finalize();
+        for pair in block.chunks(2) {
+            let c = u16::from_le_bytes([pair[0], pair[1]]) as u32 & mask;
+            if c < count {
+                challenges.push(c as i32 - curves as i32);
+                if challenges.len() == rounds {
+                    return challenges;
+                }
+            }
+        }
+    }
+
+    unreachable!("the challenges are found long before the counter wraps around")
+}
+
+/// The curve `E_c` of the challenge `c`, `E_0` for zero and the twist of `E_-c` if negative
+pub(crate) fn challenge_curve<P: CsidhParams>(curves: &[PublicKey<P>], c: i32) -> LargeUint<P> {
+    match c {
+        0 => LargeUint::from_u64(0),
+        c if c > 0 => curves[c as usize - 1].a,
+        c => {
+            let a = GaloisElement::from_large_uint(curves[(-c) as usize - 1].a);
+            (GaloisElement::from_u64(0) - a).into_large_uint()
+        }
+    }
+}
+
+/// A CSI-FiSh private key, the discrete logarithms of the public curves
+pub struct CsiFishPrivateKey<P: CsidhParams = Csidh512> {
+    keys: Vec<LargeUint<P>>,
+}
+
+impl<P: CsidhParams> Drop for CsiFishPrivateKey<P> {
+    fn drop(&mut self) {
+        self.keys.zeroize();
+    }
+}
+
+/// A CSI-FiSh public key, a list of curves
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsiFishPublicKey<P: CsidhParams = Csidh512> {
+    pub(crate) curves: Vec<PublicKey<P>>,
+}
+
+impl<P: CsidhParams> CsiFishPublicKey<P> {
+    /// Encodes the public key, the curves are encoded like `PublicKey::to_bytes` one after the
+    /// other
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.curves.iter().flat_map(|curve| curve.to_bytes().as_ref().to_vec()).collect()
+    }
+
+    /// Decodes a public key produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<CsiFishPublicKey<P>, CsidhError> {
+        let len = PublicKey::<P>::BYTES;
+        if bytes.is_empty() || !bytes.len().is_multiple_of(len) {
+            return Err(CsidhError::InvalidLength {
+                expected: (bytes.len() / len).max(1) * len,
+                actual: bytes.len(),
+            });
+        }
+
+        Ok(CsiFishPublicKey {
+            curves: bytes.chunks(len).map(PublicKey::from_bytes).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// A CSI-FiSh signature
+///
+/// The encoding is the 32 byte hash the challenges are derived from, followed by the response of
+/// each round modulo the class number in little-endian, each as long as the class number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsiFishSignature {
+    bytes: Vec<u8>,
+}
+
+impl CsiFishSignature {
+    /// The encoded signature
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Wraps an encoded signature, its length is checked when it is verified
+    pub fn from_bytes(bytes: &[u8]) -> CsiFishSignature {
+        CsiFishSignature {
+            bytes: bytes.to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::Toy;
+
+    #[test]
+    fn check_sign() {
+        let mut rng = thread_rng();
+        let scheme = CsiFish::new(ClassGroup::<Toy>::toy(), 3, 12).unwrap();
+        let (private, public) = scheme.generate_keys(&mut rng);
+        assert_eq!(public.curves.len(), 3);
+
+        let signature = scheme.sign(&private, b"message").unwrap();
+        assert_eq!(signature.as_bytes().len(), scheme.signature_bytes());
+        assert_eq!(scheme.signature_bytes(), 32 + 12);
+        assert!(scheme.verify(&public, b"message", &signature));
+        assert!(!scheme.verify(&public, b"massage", &signature));
+
+        // The key has one curve too few for these parameters
+        let wider = CsiFish::new(ClassGroup::<Toy>::toy(), 4, 12).unwrap();
+        assert_eq!(wider.sign(&private, b"message"), Err(CsidhError::InvalidParameters));
+
+        let (_, other) = scheme.generate_keys(&mut rng);
+        if other != public {
+            assert!(!scheme.verify(&other, b"message", &signature));
+        }
+
+        let mut bytes = signature.as_bytes().to_vec();
+        assert!(scheme.verify(&public, b"message", &CsiFishSignature::from_bytes(&bytes)));
+        assert!(!scheme.verify(&public, b"message", &CsiFishSignature::from_bytes(&bytes[1..])));
+        bytes[0] ^= 1;
+        assert!(!scheme.verify(&public, b"message", &CsiFishSignature::from_bytes(&bytes)));
+
+        // Responses have to be smaller than the class number
+        let mut bytes = signature.as_bytes().to_vec();
+        bytes[32] = 183;
+        assert!(!scheme.verify(&public, b"message", &CsiFishSignature::from_bytes(&bytes)));
+    }
+
+    #[test]
+    fn check_challenges() {
+        let chosen = challenges(&[7; 32], 2, 1000);
+        assert_eq!(chosen, challenges(&[7; 32], 2, 1000));
+        assert_ne!(chosen, challenges(&[8; 32], 2, 1000));
+        for c in -2..=2 {
+            assert!(chosen.contains(&c));
+        }
+        assert!(chosen.iter().all(|c| (-2..=2).contains(c)));
+    }
+
+    #[test]
+    fn check_public_key_bytes() {
+        let scheme = CsiFish::new(ClassGroup::<Toy>::toy(), 2, 1).unwrap();
+        let (_, public) = scheme.generate_keys(&mut thread_rng());
+        let bytes = public.to_bytes();
+        assert_eq!(bytes.len(), 16);
+        assert_eq!(CsiFishPublicKey::from_bytes(&bytes), Ok(public));
+        assert_eq!(CsiFishPublicKey::<Toy>::from_bytes(&bytes[1..]),
+                   Err(CsidhError::InvalidLength { expected: 8, actual: 15 }));
+        assert_eq!(CsiFishPublicKey::<Toy>::from_bytes(&[0xff; 8]), Err(CsidhError::OutOfRange));
+    }
+
+    #[test]
+    fn check_invalid() {
+        let group = ClassGroup::<Toy>::toy();
+        assert_eq!(CsiFish::new(group.clone(), 0, 1), Err(CsidhError::InvalidParameters));
+        assert_eq!(CsiFish::new(group.clone(), 1, 0), Err(CsidhError::InvalidParameters));
+        assert_eq!(CsiFish::new(group.clone(), 1 << 15, 1), Err(CsidhError::InvalidParameters));
+
+        let scheme = CsiFish::with_class_group(group);
+        let bits = |rounds: usize| 31f64.log2() * rounds as f64;
+        assert!(bits(CsiFish::<Toy>::DEFAULT_ROUNDS) >= 128.0);
+        assert!(bits(CsiFish::<Toy>::DEFAULT_ROUNDS - 1) < 128.0);
+        assert_eq!(scheme.rounds, 26);
+    }
+}
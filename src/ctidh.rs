@@ -0,0 +1,539 @@
+//! CTIDH, the constant time variant of CSIDH by Banegas et al.
+//!
+//! The primes are split into batches of consecutive primes. Instead of one bound per prime a key
+//! has one bound per batch, on the sum of the absolute exponents of its primes. The action does
+//! exactly that many isogenies per batch, and as every isogeny of a batch is computed as a
+//! Matryoshka isogeny of the largest degree in the batch, the running time does not reveal which
+//! primes of the batch the key uses.
+
+use core::ops::Range;
+
+use rand::prelude::*;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::csidh::{random_point, CsidhSharedSecret, PublicKey};
+use crate::error::CsidhError;
+use crate::galois::{GaloisElement, LargeUint};
+use crate::keyspace::{log2, KeySpace};
+use crate::montgomery::{Curve, ProjectivePoint};
+use crate::params::{Array, Csidh512, CsidhParams};
+
+/// The number of exponent vectors of length `n` whose absolute values sum up to at most `m`
+///
+/// Returns `None` if the number does not fit into 64 bits.
+fn count(n: usize, m: i8) -> Option<u64> {
+    counts(n, m).map(|row| row[m as usize])
+}
+
+/// Like `count`, for every bound `j <= m` at index `j`
+fn counts(n: usize, m: i8) -> Option<[u64; 128]> {
+    let m = m as usize;
+
+    // row[j] is the number for length i and bound j, for i = 0 there is only the empty vector
+    let mut row = [1u64; 128];
+    for _ in 0..n {
+        let mut next = [0u64; 128];
+        let mut below = 0u64;
+        for j in 0..=m {
+            // A zero exponent leaves the bound j, +-v leaves j - v for v = 1..=j
+            next[j] = row[j].checked_add(below.checked_mul(2)?)?;
+            below = below.checked_add(row[j])?;
+        }
+        row = next;
+    }
+
+    Some(row)
+}
+
+/// The set CTIDH private keys are sampled from
+///
+/// The primes are split into batches of consecutive primes, each with a bound on the sum of the
+/// absolute exponents of its primes. The batches and bounds are public.
+///
+/// ## Example
+///
+/// ```rust
+/// # use csidh::CtidhKeySpace;
+/// let key_space = CtidhKeySpace::<csidh::Csidh512>::uniform(5, 14).unwrap();
+/// assert_eq!(key_space.num_batches(), 15);
+/// assert!(key_space.bits() > 256.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CtidhKeySpace<P: CsidhParams = Csidh512> {
+    // The bound of each batch at the index of its first prime, -1 for the other primes
+    bounds: P::Exponents,
+}
+
+impl<P: CsidhParams> Default for CtidhKeySpace<P> {
+    /// Batches of five primes with the smallest common bound that gives at least as many keys
+    /// as the default `KeySpace`
+    fn default() -> CtidhKeySpace<P> {
+        let target = KeySpace::<P>::default().bits();
+        (1..=i8::MAX)
+            .map(|bound| CtidhKeySpace::uniform(5, bound).expect("the bounds are valid"))
+            .find(|key_space| key_space.bits() >= target)
+            .expect("a bound of 127 gives enough keys")
+    }
+}
+
+impl<P: CsidhParams> CtidhKeySpace<P> {
+    /// Splits the primes into batches of `sizes[i]` consecutive primes, the exponents of batch `i`
+    /// have absolute values summing up to at most `bounds[i]`
+    ///
+    /// The sizes have to add up to the number of primes. Every bound has to be nonnegative and
+    /// small enough that the number of exponent vectors of its batch fits into 64 bits.
+    pub fn new(sizes: &[usize], bounds: &[i8]) -> Result<CtidhKeySpace<P>, CsidhError> {
+        let total: usize = sizes.iter().sum();
+        if total != P::NUM_PRIMES {
+            return Err(CsidhError::InvalidLength {
+                expected: P::NUM_PRIMES,
+                actual: total,
+            });
+        }
+
+        if bounds.len() != sizes.len() {
+            return Err(CsidhError::InvalidLength {
+                expected: sizes.len(),
+                actual: bounds.len(),
+            });
+        }
+
+        let mut key_space = CtidhKeySpace::<P> {
+            bounds: P::Exponents::filled(-1),
+        };
+
+        let mut start = 0;
+        for (i, (&size, &bound)) in sizes.iter().zip(bounds).enumerate() {
+            if size == 0 || bound < 0 || count(size, bound).is_none() {
+                return Err(CsidhError::InvalidBounds { index: i });
+            }
+            key_space.bounds[start] = bound;
+            start += size;
+        }
+
+        Ok(key_space)
+    }
+
+    /// Batches of `size` consecutive primes, the last one may be smaller, all with `bound`
+    pub fn uniform(size: usize, bound: i8) -> Result<CtidhKeySpace<P>, CsidhError> {
+        if size == 0 || bound < 0 || count(size, bound).is_none() {
+            return Err(CsidhError::InvalidBounds { index: 0 });
+        }
+
+        let mut key_space = CtidhKeySpace::<P> {
+            bounds: P::Exponents::filled(-1),
+        };
+        for start in (0..P::NUM_PRIMES).step_by(size) {
+            key_space.bounds[start] = bound;
+        }
+
+        Ok(key_space)
+    }
+
+    /// The number of batches
+    pub fn num_batches(&self) -> usize {
+        self.batches().count()
+    }
+
+    /// The prime indices and the bound of each batch
+    fn batches(&self) -> impl DoubleEndedIterator<Item = (Range<usize>, i8)> + '_ {
+        let bounds = self.bounds.as_ref();
+        (0..P::NUM_PRIMES).filter(move |&i| bounds[i] >= 0).map(move |start| {
+            let end = (start + 1..P::NUM_PRIMES).find(|&i| bounds[i] >= 0).unwrap_or(P::NUM_PRIMES);
+            (start..end, bounds[start])
+        })
+    }
+
+    /// Whether the absolute exponents of every batch sum up to at most its bound
+    pub fn contains(&self, exponents: &[i8]) -> bool {
+        self.check(exponents).is_ok()
+    }
+
+    fn check(&self, exponents: &[i8]) -> Result<(), CsidhError> {
+        if exponents.len() != P::NUM_PRIMES {
+            return Err(CsidhError::InvalidLength {
+                expected: P::NUM_PRIMES,
+                actual: exponents.len(),
+            });
+        }
+
+        for (batch, bound) in self.batches() {
+            let mut sum = 0i32;
+            for i in batch {
+                sum += (exponents[i] as i32).abs();
+                if sum > bound as i32 {
+                    return Err(CsidhError::ExponentOutOfRange { index: i });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The base 2 logarithm of the number of private keys
+    pub fn bits(&self) -> f64 {
+        self.batches()
+            .map(|(batch, bound)| log2(count(batch.len(), bound).unwrap()))
+            .sum()
+    }
+
+    /// Samples an exponent vector uniformly from the key space into `exponents`
+    ///
+    /// The work only depends on the key space. Every candidate exponent is tried for every prime
+    /// and the numbers of vectors are read by scanning a whole table, the secret index and bounds
+    /// only go into masks.
+    fn sample<R: CryptoRng + Rng>(&self, exponents: &mut P::Exponents, rng: &mut R) {
+        for (batch, bound) in self.batches() {
+            // Pick one of the vectors of the batch and find out which one it is, exponent by
+            // exponent
+            let mut index = rng.gen_range(0, count(batch.len(), bound).unwrap());
+            let mut left = bound;
+            for i in batch.clone() {
+                let rest = counts(batch.end - i - 1, bound).unwrap();
+                let mut e = 0i8;
+                let mut found = false;
+
+                // The candidates in the order 0, -1, 1, -2, 2, ...
+                for c in 0..=2 * bound as i32 {
+                    let v = if c % 2 == 1 { -(c + 1) / 2 } else { c / 2 };
+
+                    // The number of vectors starting with v, zero if |v| exceeds the bound left
+                    let target = left as i32 - v.abs();
+                    let mut n = 0;
+                    for (j, &r) in rest.iter().enumerate().take(bound as usize + 1) {
+                        n |= select(target == j as i32, r, 0);
+                    }
+
+                    let take = !found & (index < n);
+                    e |= (take as i8).wrapping_neg() & v as i8;
+                    found |= take;
+                    index -= select(found, 0, n);
+                }
+
+                exponents[i] = e;
+                left -= e.abs();
+            }
+        }
+    }
+}
+
+/// Returns whether to keep a successful isogeny of degree `l` in a batch whose smallest degree is
+/// `l_min`
+///
+/// A random point gives an isogeny of degree `l` with probability `1 - 1 / l`, so the rate of
+/// failures would reveal `l`. Keeping the isogeny with probability
+/// `(1 - 1 / l_min) / (1 - 1 / l)` makes it `1 - 1 / l_min` for every prime of the batch.
+fn keep_isogeny<R: CryptoRng + Rng>(rng: &mut R, l: u64, l_min: u64) -> bool {
+    let num = ((l_min - 1) * l) as u128;
+    let den = (l_min * (l - 1)) as u128;
+    (rng.gen::<u64>() as u128) * den < num << 64
+}
+
+/// Evaluates the CTIDH group action, the work done only depends on the key space
+fn action<P: CsidhParams, R: CryptoRng + Rng>(curve: &Curve<GaloisElement<P>>, private: &P::Exponents,
+                                              key_space: &CtidhKeySpace<P>, rng: &mut R)
+    -> Result<LargeUint<P>, CsidhError>
+{
+    // Real isogenies left per prime, secret
+    let mut e = Zeroizing::new(*private);
+    // Isogenies left per batch at the index of its first prime, real or dummy, these are public
+    let mut counts = key_space.bounds;
+    // Whether a prime is the one its batch uses in this round, secret
+    let mut selected = Zeroizing::new(P::Exponents::filled(0));
+
+    let mut p_curve = Zeroizing::new(ProjectivePoint::new(curve.a, GaloisElement::from_u64(1)));
+
+    while counts.as_ref().iter().any(|&c| c > 0) {
+        // Every batch uses the first prime with an isogeny left, or does a dummy isogeny of its
+        // first prime if there is none. Primes of finished batches are multiplied away.
+        let mut k = Zeroizing::new(LargeUint::from_u64(4));
+        for (batch, _) in key_space.batches() {
+            let active = counts[batch.start] > 0;
+            let mut found = !active;
+            for i in batch.clone() {
+                let take = !found & (e[i] != 0);
+                selected[i] = take as i8;
+                found |= take;
+            }
+            selected[batch.start] |= !found as i8;
+
+            for i in batch {
+                k.mul_with_u64(select(selected[i] != 0, 1, P::PRIMES[i]));
+            }
+        }
+
+        let bits = P::PBITS + 1;
+        // The batches that do an isogeny in this round
+        let active = counts;
+
+        let mut points = Zeroizing::new([
+            random_point(rng, &p_curve.x, false).ladder2_constant_time(&p_curve, &k, bits),
+            random_point(rng, &p_curve.x, true).ladder2_constant_time(&p_curve, &k, bits),
+        ]);
+
+        for (batch, _) in key_space.batches().rev().filter(|(b, _)| active[b.start] > 0) {
+            let l_min = P::PRIMES[batch.start];
+            let l_max = P::PRIMES[batch.end - 1];
+
+            // The selected prime of the batch, whether its isogeny is real and in which direction
+            // it goes
+            let mut l = 0;
+            let mut real = false;
+            let mut negative = false;
+            for i in batch.clone() {
+                let chosen = selected[i] != 0;
+                l |= select(chosen, P::PRIMES[i], 0);
+                real |= chosen & (e[i] != 0);
+                negative |= chosen & (e[i] < 0);
+            }
+
+            let [p0, p1] = &mut *points;
+            ProjectivePoint::conditional_swap(p0, p1, negative);
+
+            // The selected primes of the batches that come later in this round, the length of the
+            // ladder is bounded by their largest primes
+            let mut cof = Zeroizing::new(LargeUint::from_u64(1));
+            let mut cof_bound = LargeUint::<P>::from_u64(1);
+            for (other, _) in key_space.batches().take_while(|(b, _)| b.start < batch.start) {
+                if active[other.start] > 0 {
+                    for i in other.clone() {
+                        cof.mul_with_u64(select(selected[i] != 0, P::PRIMES[i], 1));
+                    }
+                    cof_bound.mul_with_u64(P::PRIMES[other.end - 1]);
+                }
+            }
+
+            let l_uint = Zeroizing::new(LargeUint::from_u64(l));
+            let l_bits = 64 - l_max.leading_zeros() as u64;
+            points[1] = points[1].ladder2_constant_time(&p_curve, &l_uint, l_bits);
+
+            let kernel = Zeroizing::new(points[0].ladder2_constant_time(&p_curve, &cof,
+                                                                        cof_bound.bits()));
+            let done = !kernel.is_infinity() & keep_isogeny(rng, l, l_min);
+
+            let without_l = points[0].ladder2_constant_time(&p_curve, &l_uint, l_bits);
+            if done {
+                let mut new_curve = p_curve.clone();
+                let mut new_points = points.clone();
+                Curve::isogeny_points_matryoshka(&mut new_curve, &mut new_points, &kernel, l, l_max);
+
+                // A dummy isogeny keeps the curve and only removes l from the order of the point
+                p_curve.conditional_assign(&new_curve, real);
+                points[0] = without_l;
+                points[0].conditional_assign(&new_points[0], real);
+                points[1].conditional_assign(&new_points[1], real);
+
+                for i in batch.clone() {
+                    let step = (selected[i] != 0) & real;
+                    e[i] -= (step & (e[i] > 0)) as i8;
+                    e[i] += (step & (e[i] < 0)) as i8;
+                }
+                counts[batch.start] -= 1;
+            } else {
+                points[0] = without_l;
+            }
+
+            let [p0, p1] = &mut *points;
+            ProjectivePoint::conditional_swap(p0, p1, negative);
+        }
+
+        if p_curve.z == GaloisElement::from_u64(0) {
+            return Err(CsidhError::DegenerateCurve);
+        }
+
+        p_curve.normalize();
+    }
+
+    return Ok(p_curve.x.into_large_uint());
+}
+
+/// `a` if `choice` is true and `b` otherwise, without branching on `choice`
+fn select(choice: bool, a: u64, b: u64) -> u64 {
+    let mask = (choice as u64).wrapping_neg();
+    (a & mask) | (b & !mask)
+}
+
+/// A private key for CTIDH with the parameter set `P`
+///
+/// The public keys and shared secrets are the same as for `PrivateKey`, so a CTIDH key can do a
+/// key exchange with a classic CSIDH key of the same parameter set. The group action always runs
+/// in constant time.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// # use csidh::{CtidhKeySpace, CtidhPrivateKey};
+/// # let mut rng: rand::rngs::ThreadRng = unimplemented!();
+/// let private: CtidhPrivateKey = CtidhPrivateKey::generate_new(&CtidhKeySpace::default(), &mut rng);
+/// let public = private.get_public_key();
+/// ```
+pub struct CtidhPrivateKey<P: CsidhParams = Csidh512> {
+    key: P::Exponents,
+    key_space: CtidhKeySpace<P>,
+}
+
+impl<P: CsidhParams> CtidhPrivateKey<P> {
+    /// Creates a private key from its exponent vector, which has to be in `key_space`
+    pub fn from_exponents(key_space: &CtidhKeySpace<P>, exponents: &[i8])
+        -> Result<CtidhPrivateKey<P>, CsidhError>
+    {
+        key_space.check(exponents)?;
+
+        let mut private = CtidhPrivateKey::<P> {
+            key: P::Exponents::filled(0),
+            key_space: *key_space,
+        };
+        private.key.as_mut().copy_from_slice(exponents);
+
+        Ok(private)
+    }
+
+    /// Generates a new private key from the given key space
+    pub fn generate_new<S: CryptoRng + Rng>(key_space: &CtidhKeySpace<P>, rng: &mut S)
+        -> CtidhPrivateKey<P>
+    {
+        let mut private = CtidhPrivateKey::<P> {
+            key: P::Exponents::filled(0),
+            key_space: *key_space,
+        };
+
+        key_space.sample(&mut private.key, rng);
+
+        private
+    }
+
+    /// Gets the associated public key
+    #[cfg(feature = "std")]
+    pub fn get_public_key(&self) -> PublicKey<P> {
+        self.get_public_key_with_rng(&mut thread_rng())
+    }
+
+    /// Gets the associated public key, drawing the randomness for the group action from `rng`
+    pub fn get_public_key_with_rng<R: CryptoRng + Rng>(&self, rng: &mut R) -> PublicKey<P> {
+        let curve = Curve::new(0u32.into(), 1u32.into());
+        let a = action(&curve, &self.key, &self.key_space, rng)
+            .expect("the starting curve is supersingular");
+
+        PublicKey {
+            a
+        }
+    }
+
+    /// Computes the shared secret with another public key
+    ///
+    /// The other key is validated first, an error is returned if it does not describe a
+    /// supersingular curve.
+    #[cfg(feature = "std")]
+    pub fn get_shared_secret(&self, other: &PublicKey<P>) -> Result<CsidhSharedSecret<P>, CsidhError> {
+        self.get_shared_secret_with_rng(other, &mut thread_rng())
+    }
+
+    /// Computes the shared secret with another public key, drawing the randomness for the
+    /// validation and the group action from `rng`
+    pub fn get_shared_secret_with_rng<R: CryptoRng + Rng>(&self, other: &PublicKey<P>, rng: &mut R)
+        -> Result<CsidhSharedSecret<P>, CsidhError>
+    {
+        if !other.validate_with_rng(rng) {
+            return Err(CsidhError::InvalidPublicKey);
+        }
+
+        let their_curve = Curve::new(other.a, 1u32.into());
+        let s = Zeroizing::new(action(&their_curve, &self.key, &self.key_space, rng)?);
+        Ok(CsidhSharedSecret::new(&s))
+    }
+}
+
+impl<P: CsidhParams> Drop for CtidhPrivateKey<P> {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::csidh::CsidhPrivateKey;
+
+    type CtidhKeySpace = super::CtidhKeySpace<Csidh512>;
+
+    #[test]
+    fn check_count() {
+        assert_eq!(count(0, 5), Some(1));
+        assert_eq!(count(1, 5), Some(11));
+        assert_eq!(count(3, 0), Some(1));
+        assert_eq!(count(2, 1), Some(5));
+        assert_eq!(count(5, 4), Some(681));
+        assert_eq!(count(100, 100), None);
+    }
+
+    #[test]
+    fn check_key_space() {
+        let key_space = CtidhKeySpace::uniform(5, 3).unwrap();
+        assert_eq!(key_space.num_batches(), 15);
+        assert_eq!(key_space.batches().last(), Some((70..74, 3)));
+        assert!((key_space.bits() - 14.0 * 231f64.log2() - 129f64.log2()).abs() < 1e-9);
+
+        let default = CtidhKeySpace::default();
+        assert!(default.bits() >= KeySpace::<Csidh512>::default().bits());
+
+        let mut sizes = [1usize; 74];
+        sizes[0] = 2;
+        assert_eq!(CtidhKeySpace::new(&sizes, &[1; 74]).err(),
+                   Some(CsidhError::InvalidLength { expected: 74, actual: 75 }));
+        assert_eq!(CtidhKeySpace::new(&sizes[..73], &[1; 74]).err(),
+                   Some(CsidhError::InvalidLength { expected: 73, actual: 74 }));
+        let mut bounds = [1i8; 73];
+        bounds[7] = -1;
+        assert_eq!(CtidhKeySpace::new(&sizes[..73], &bounds).err(),
+                   Some(CsidhError::InvalidBounds { index: 7 }));
+        assert_eq!(CtidhKeySpace::uniform(0, 1).err(), Some(CsidhError::InvalidBounds { index: 0 }));
+
+        let mut exponents = [0i8; 74];
+        exponents[70] = -2;
+        exponents[73] = 1;
+        assert!(key_space.contains(&exponents));
+        exponents[72] = 1;
+        assert!(!key_space.contains(&exponents));
+        assert_eq!(CtidhPrivateKey::from_exponents(&key_space, &exponents).err(),
+                   Some(CsidhError::ExponentOutOfRange { index: 73 }));
+    }
+
+    #[test]
+    fn check_sample() {
+        let mut rng = thread_rng();
+        let key_space = CtidhKeySpace::uniform(3, 2).unwrap();
+
+        // Every one of the 25 vectors of a batch of size 3 with bound 2 shows up
+        let mut seen = std::collections::HashSet::new();
+        let mut exponents = [0i8; 74];
+        for _ in 0..1000 {
+            key_space.sample(&mut exponents, &mut rng);
+            assert!(key_space.contains(&exponents));
+            seen.insert([exponents[0], exponents[1], exponents[2]]);
+        }
+        assert_eq!(seen.len() as u64, count(3, 2).unwrap());
+    }
+
+    #[test]
+    fn check_action() {
+        let mut rng = thread_rng();
+        let key_space = CtidhKeySpace::uniform(5, 2).unwrap();
+        let private = CtidhPrivateKey::generate_new(&key_space, &mut rng);
+
+        // Every exponent is at most 2, so the classic action can check the result
+        let classic = CsidhPrivateKey::from_exponents(&private.key).unwrap();
+        assert_eq!(private.get_public_key(), classic.get_public_key());
+    }
+
+    #[test]
+    fn check_key_exchange() {
+        let mut rng = thread_rng();
+        let key_space = CtidhKeySpace::uniform(8, 2).unwrap();
+        let alice = CtidhPrivateKey::generate_new(&key_space, &mut rng);
+        let bob = CsidhPrivateKey::from_exponents(&[1; 74]).unwrap();
+
+        let alice_shared = alice.get_shared_secret(&bob.get_public_key()).unwrap();
+        let bob_shared = bob.get_shared_secret(&alice.get_public_key()).unwrap();
+        assert_eq!(alice_shared, bob_shared);
+    }
+}
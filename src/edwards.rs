@@ -1,30 +1,134 @@
-use num_bigint::BigUint;
-use num_traits::identities::{One, Zero};
+use crate::field::PrimeField;
+use crate::montgomery::ProjectivePoint;
 
-use crate::ffield::{Field, FieldElement};
+/// Like `montgomery::Curve::isogeny_points`, but computes the image curve on the twisted Edwards
+/// model `a x^2 + y^2 = 1 + d x^2 y^2`
+///
+/// The curve and the points are given and returned in Montgomery form, so both versions can
+/// be used interchangeably. With `(Y_i : Z_i)` the Edwards y-coordinates of `[i] K` for
+/// `1 <= i <= (l - 1) / 2`, Moody and Shumow show that the image of `(a : d)` is
+/// `(a^l * (Z_1 * ... * Z_s)^8 : d^l * (Y_1 * ... * Y_s)^8)`. This needs fewer
+/// multiplications per kernel point than the Montgomery formula.
+pub(crate) fn isogeny_points<F: PrimeField, const N: usize>(a: &mut ProjectivePoint<F>,
+                                                            points: &mut [ProjectivePoint<F>; N],
+                                                            k: &ProjectivePoint<F>, l: u64)
+{
+    // The Montgomery curve (A : C) is the Edwards curve (A + 2C : A - 2C), and a point
+    // (X : Z) has the Edwards y-coordinate (X - Z : X + Z)
+    let two_c = a.z + a.z;
+    let ed_a = a.x + two_c;
+    let ed_d = a.x - two_c;
 
-use crate::montgomery::Curve as MontgomeryCurve;
+    let mut prod_y = k.x - k.z;
+    let mut prod_z = k.x + k.z;
+    let mut tmp0;
+    let mut tmp1;
+    let mut q = [ProjectivePoint::new(F::from_u64(1), F::from_u64(1)); N];
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct EdwardsCurve {
-    pub field: Field,
-    pub a: FieldElement,
-    pub d: FieldElement,
-}
+    for (q, p) in q.iter_mut().zip(points.iter()) {
+        q.x =  p.x * k.x;
+        tmp0 = p.z * k.z;
+        q.x.sub_from(&tmp0);
+
+        q.z =  p.x * k.z;
+        tmp0 = p.z * k.x;
+        q.z.sub_from(&tmp0);
+    }
+
+    let mut m = [*k; 3];
+    m[1] = k.double2(a);
+
+    for i in 1..(l as usize / 2) {
+        if i >= 2 {
+            m[i % 3] = m[(i -1) % 3].add(k, &m[(i - 2) % 3]);
+        }
+
+        tmp0 = m[i % 3].x - m[i % 3].z;
+        prod_y.mul_with(&tmp0);
+        tmp0 = m[i % 3].x + m[i % 3].z;
+        prod_z.mul_with(&tmp0);
+
+        for (q, p) in q.iter_mut().zip(points.iter()) {
+            tmp0 = p.x * m[i % 3].x;
+            tmp1 = p.z * m[i % 3].z;
+            tmp0.sub_from(&tmp1);
+            q.x.mul_with(&tmp0);
+
+            tmp0 = p.x * m[i % 3].z;
+            tmp1 = p.z * m[i % 3].x;
+            tmp0.sub_from(&tmp1);
+            q.z.mul_with(&tmp0);
+        }
+    }
+
+    for _ in 0..3 {
+        prod_y.square();
+        prod_z.square();
+    }
 
-impl EdwardsCurve {
-    pub fn to_montgomery(&self) -> MontgomeryCurve {
-        let a = &self.field.get(2u32) * (&self.a + &self.d);
-        let c = &self.a - &self.d;
+    let ed_a = ed_a.pow_u64(l) * prod_z;
+    let ed_d = ed_d.pow_u64(l) * prod_y;
 
-        MontgomeryCurve::new(self.field.clone(), a, c);
+    a.x = ed_a + ed_d;
+    a.x.add_from(&{a.x});
+    a.z = ed_a - ed_d;
+
+    for (q, p) in q.iter_mut().zip(points.iter_mut()) {
+        q.x.square();
+        q.z.square();
+        p.x.mul_with(&q.x);
+        p.z.mul_with(&q.z);
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct EdwardsPoint {
-    curve: Curve,
-    x: FieldElement,
-    y: FieldElement,
-    z: FieldElement,
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::galois::{GaloisElement, LargeUint};
+    use crate::montgomery::{Curve as MontgomeryCurve, Point as MontgomeryPoint};
+    use crate::params::{Csidh512, CsidhParams};
+
+    type F = GaloisElement<Csidh512>;
+
+    fn montgomery_point() -> MontgomeryPoint<F> {
+        let x = LargeUint::parse_bytes(b"2051044887188588280366899510711463515184102432059522841387541984999186019238289110841661333718393379209806643406155944602233875537370058705956384966209858").unwrap();
+        let y = LargeUint::parse_bytes(b"2999054700883294606115636709285947688603015463995111523694534197644452886751843273757676343103953201273958036952062931228773567734286840492294219977378136").unwrap();
+
+        let curve = MontgomeryCurve::new(0u32.into(), 1u32.into());
+        MontgomeryPoint::new(curve, x, y)
+    }
+
+    #[test]
+    fn check_isogeny() {
+        let curve = ProjectivePoint::new(F::from_u64(0), F::from_u64(1));
+        let point = montgomery_point();
+        let point = ProjectivePoint::new(point.x, point.z);
+
+        for &l in [3, 5, 587].iter() {
+            // (p + 1) / l kills everything but the l-torsion
+            let mut cof = LargeUint::from_u64(4);
+            for &other in Csidh512::PRIMES.iter().filter(|&&other| other != l) {
+                cof.mul_with_u64(other);
+            }
+
+            let kernel = (1..)
+                .map(F::from_u64)
+                .filter(|x| MontgomeryCurve::right_side(&curve.x, x).is_square())
+                .map(|x| ProjectivePoint::new(x, F::from_u64(1)).ladder2(&curve, &cof))
+                .find(|k| !k.is_infinity())
+                .unwrap();
+
+            let mut montgomery = (curve, point);
+            MontgomeryCurve::isogeny(&mut montgomery.0, &mut montgomery.1, &kernel, l);
+            let mut edwards = (curve, [point]);
+            isogeny_points(&mut edwards.0, &mut edwards.1, &kernel, l);
+            let mut edwards = (edwards.0, edwards.1[0]);
+
+            for (c, p) in [&mut montgomery, &mut edwards] {
+                c.normalize();
+                p.normalize();
+            }
+            assert_eq!(montgomery, edwards);
+        }
+    }
 }
@@ -0,0 +1,62 @@
+/// Errors that can occur when working with CSIDH keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsidhError {
+    /// The input did not have the expected number of bytes
+    InvalidLength {
+        expected: usize,
+        actual: usize,
+    },
+    /// The encoded value is not smaller than the field prime
+    OutOfRange,
+    /// The public key does not describe a supersingular curve
+    InvalidPublicKey,
+    /// A decimal string contained something other than the digits `0` to `9`
+    InvalidDigit(u8),
+    /// A point at infinity has no affine coordinates
+    PointAtInfinity,
+    /// An exponent of a private key is outside of the allowed bounds
+    ///
+    /// Only the index is kept, the exponent itself is secret.
+    ExponentOutOfRange {
+        index: usize,
+    },
+    /// The group action ran into a degenerate curve
+    DegenerateCurve,
+    /// The exponent range of a key space is empty or reaches `-128`
+    InvalidBounds {
+        index: usize,
+    },
+    /// The basis does not describe the relation lattice of the class group
+    InvalidClassGroup,
+    /// The parameters of a signature scheme are out of range
+    InvalidParameters,
+    /// Every attempt at a signature was aborted
+    SigningAborted,
+}
+
+impl core::fmt::Display for CsidhError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            CsidhError::InvalidLength { expected, actual } => {
+                write!(f, "expected {} bytes, got {}", expected, actual)
+            }
+            CsidhError::OutOfRange => write!(f, "value is not smaller than p"),
+            CsidhError::InvalidPublicKey => write!(f, "public key is not a supersingular curve"),
+            CsidhError::InvalidDigit(d) => write!(f, "invalid decimal digit 0x{:02x}", d),
+            CsidhError::PointAtInfinity => write!(f, "point at infinity has no affine coordinates"),
+            CsidhError::ExponentOutOfRange { index } => {
+                write!(f, "exponent at index {} is out of range", index)
+            }
+            CsidhError::DegenerateCurve => write!(f, "group action reached a degenerate curve"),
+            CsidhError::InvalidBounds { index } => {
+                write!(f, "exponent bounds at index {} are invalid", index)
+            }
+            CsidhError::InvalidClassGroup => write!(f, "basis does not match the class group"),
+            CsidhError::InvalidParameters => write!(f, "signature parameters are out of range"),
+            CsidhError::SigningAborted => write!(f, "every signature attempt was aborted"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CsidhError {}
@@ -0,0 +1,98 @@
+use core::fmt::Debug;
+use core::ops::{Add, Sub, Mul, Div};
+use rand::{CryptoRng, Rng};
+use zeroize::Zeroize;
+
+/// An unsigned integer, used for scalars and for the encoding of field elements
+pub trait FieldUint: Copy + Debug + Eq + Ord + From<u32> {
+    fn from_u64(u: u64) -> Self;
+
+    /// The number of significant bits
+    fn bits(&self) -> u64;
+
+    /// Whether the bit `i` is set
+    fn bit(&self, i: u64) -> bool;
+}
+
+impl FieldUint for u64 {
+    fn from_u64(u: u64) -> u64 {
+        u
+    }
+
+    fn bits(&self) -> u64 {
+        64 - self.leading_zeros() as u64
+    }
+
+    fn bit(&self, i: u64) -> bool {
+        i < 64 && (self >> i) & 1 == 1
+    }
+}
+
+/// The arithmetic the curve code needs from a prime field
+#[allow(dead_code)]
+pub trait PrimeField: Copy + Debug + PartialEq + Zeroize
+    + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self>
+{
+    /// The integers the elements are encoded as
+    type Uint: FieldUint;
+
+    fn zero() -> Self {
+        Self::from_u64(0)
+    }
+
+    fn one() -> Self;
+
+    fn from_u64(u: u64) -> Self;
+
+    /// Decodes an element from its representative, which has to be smaller than the prime
+    fn from_uint(u: Self::Uint) -> Self;
+
+    /// Encodes an element as its representative between zero and the prime
+    fn into_uint(&self) -> Self::Uint;
+
+    /// Samples an element uniformly at random
+    fn random_element<R: Rng + CryptoRng>(rng: &mut R) -> Self;
+
+    fn add_from(&mut self, other: &Self);
+
+    fn sub_from(&mut self, other: &Self);
+
+    fn mul_with(&mut self, other: &Self);
+
+    /// Squares `self` in place and returns the result
+    fn square(&mut self) -> Self;
+
+    /// Inverts `self` in place, zero stays zero
+    fn inverse(&mut self);
+
+    /// Like `inverse`, but the running time may depend on `self`, so only for public values
+    fn inverse_vartime(&mut self) {
+        self.inverse()
+    }
+
+    /// Raises `self` to a small public power
+    fn pow_u64(&self, e: u64) -> Self {
+        let mut ret = Self::one();
+        for i in (0..64 - e.leading_zeros()).rev() {
+            ret.square();
+            if (e >> i) & 1 == 1 {
+                ret.mul_with(self);
+            }
+        }
+        ret
+    }
+
+    /// Whether `self` is a non-zero quadratic residue
+    fn is_square(&self) -> bool;
+
+    /// Like `is_square`, but the running time may depend on `self`, so only for public values
+    fn is_square_vartime(&self) -> bool {
+        self.is_square()
+    }
+
+    /// Sets `self` to `other` if `choice` is true, without branching on `choice`
+    fn conditional_assign(&mut self, other: &Self, choice: bool);
+
+    /// Swaps `a` and `b` if `choice` is true, without branching on `choice`
+    fn conditional_swap(a: &mut Self, b: &mut Self, choice: bool);
+}
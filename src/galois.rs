@@ -1,47 +1,67 @@
-use std::ops::{Add, Sub, Mul, Div};
+use core::ops::{Add, Sub, Mul, Div};
 use rand::{CryptoRng, Rng};
+use zeroize::Zeroize;
 
-use crate::global::*;
-
-pub const LIMBS: usize = 8;
+use crate::error::CsidhError;
+use crate::field::{FieldUint, PrimeField};
+use crate::inversion;
+use crate::legendre;
+use crate::params::{Array, CsidhParams};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct LargeUint {
-    pub elements: [u64; LIMBS],
+pub struct LargeUint<P: CsidhParams> {
+    pub elements: P::Limbs,
 }
 
-impl LargeUint {
-    pub fn new() -> LargeUint {
+impl<P: CsidhParams> LargeUint<P> {
+    #[allow(dead_code)]
+    pub fn new() -> LargeUint<P> {
         LargeUint {
-            elements: [0; LIMBS],
+            elements: P::Limbs::filled(0),
         }
     }
 
-    pub fn from_u64(u: u64) -> LargeUint {
+    pub fn from_u64(u: u64) -> LargeUint<P> {
+        let mut elements = P::Limbs::filled(0);
+        elements[0] = u;
         LargeUint {
-            elements: [u, 0, 0, 0, 0, 0, 0, 0],
+            elements,
         }
     }
 
-    pub fn as_bytes(&self) -> Vec<u8> {
+    pub fn as_bytes(&self) -> P::Bytes {
         use byteorder::{ByteOrder, LittleEndian};
-        let mut bytes = vec![0; 8 * LIMBS];
+        let mut bytes = P::Bytes::filled(0);
 
-        LittleEndian::write_u64_into(&self.elements, &mut bytes[..]);
+        LittleEndian::write_u64_into(self.elements.as_ref(), bytes.as_mut());
         return bytes;
     }
 
-    pub fn parse_bytes(s: &[u8]) -> LargeUint {
-        s.iter().fold(LargeUint::new(), |mut acc, x| {
+    pub fn from_bytes(bytes: &[u8]) -> LargeUint<P> {
+        use byteorder::{ByteOrder, LittleEndian};
+        let mut elements = P::Limbs::filled(0);
+
+        LittleEndian::read_u64_into(bytes, elements.as_mut());
+        LargeUint {
+            elements,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn parse_bytes(s: &[u8]) -> Result<LargeUint<P>, CsidhError> {
+        s.iter().try_fold(LargeUint::new(), |mut acc, &x| {
+            if !x.is_ascii_digit() {
+                return Err(CsidhError::InvalidDigit(x));
+            }
             acc.mul_with_u64(10);
             acc.add_from(&LargeUint::from_u64((x - b'0') as u64));
-            acc
+            Ok(acc)
         })
     }
 
-    pub fn add_from(&mut self, other: &LargeUint) -> bool {
+    pub fn add_from(&mut self, other: &LargeUint<P>) -> bool {
         let mut carry: bool = false;
-        for i in 0..LIMBS {
+        for i in 0..P::LIMBS {
             let (temp, c) = self.elements[i].overflowing_add(carry as u64);
             carry = c;
             let (res, c) = temp.overflowing_add(other.elements[i]);
@@ -52,9 +72,9 @@ impl LargeUint {
         return carry;
     }
 
-    fn sub_from(&mut self, other: &LargeUint) -> bool {
+    pub(crate) fn sub_from(&mut self, other: &LargeUint<P>) -> bool {
         let mut carry: bool = false;
-        for i in 0..LIMBS {
+        for i in 0..P::LIMBS {
             let (temp, c) = self.elements[i].overflowing_sub(carry as u64);
             carry = c;
             let (res, c) = temp.overflowing_sub(other.elements[i]);
@@ -67,7 +87,7 @@ impl LargeUint {
 
     pub fn mul_with_u64(&mut self, other: u64) {
         let mut c = 0u64;
-        for i in 0..LIMBS {
+        for i in 0..P::LIMBS {
             let t = self.elements[i] as u128 * other as u128 + c as u128;
             c = (t >> 64) as u64;
             self.elements[i] = t as u64;
@@ -75,7 +95,7 @@ impl LargeUint {
     }
 
     pub fn bits(&self) -> u64 {
-        for i in (0..LIMBS).rev() {
+        for i in (0..P::LIMBS).rev() {
             if self.elements[i] == 0 {
                 continue;
             }
@@ -86,22 +106,56 @@ impl LargeUint {
     }
 
     pub fn bit(&self, i: u64) -> bool {
-        (self.elements[i as usize / 64] >> i % 64) & 1 == 1
+        (self.elements[i as usize / 64] >> (i % 64)) & 1 == 1
+    }
+}
+
+impl<P: CsidhParams> FieldUint for LargeUint<P> {
+    fn from_u64(u: u64) -> LargeUint<P> {
+        LargeUint::from_u64(u)
+    }
+
+    fn bits(&self) -> u64 {
+        LargeUint::bits(self)
+    }
+
+    fn bit(&self, i: u64) -> bool {
+        LargeUint::bit(self, i)
     }
 }
 
-impl std::convert::From<u32> for LargeUint {
-    fn from(u: u32) -> LargeUint {
+impl<P: CsidhParams> Zeroize for LargeUint<P> {
+    fn zeroize(&mut self) {
+        self.elements.zeroize();
+    }
+}
+
+impl<P: CsidhParams> PartialOrd for LargeUint<P> {
+    fn partial_cmp(&self, other: &LargeUint<P>) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<P: CsidhParams> Ord for LargeUint<P> {
+    fn cmp(&self, other: &LargeUint<P>) -> core::cmp::Ordering {
+        self.elements.as_ref().iter().rev().cmp(other.elements.as_ref().iter().rev())
+    }
+}
+
+impl<P: CsidhParams> Eq for LargeUint<P> {}
+
+impl<P: CsidhParams> core::convert::From<u32> for LargeUint<P> {
+    fn from(u: u32) -> LargeUint<P> {
         LargeUint::from_u64(u as u64)
     }
 }
 
-impl std::fmt::Display for LargeUint {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl<P: CsidhParams> core::fmt::Display for LargeUint<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         write!(f, "[")?;
-        for i in 0..LIMBS {
+        for i in 0..P::LIMBS {
             write!(f, "0x{:016x}", self.elements[i])?;
-            if i != LIMBS - 1 {
+            if i != P::LIMBS - 1 {
                 write!(f, " ")?;
             }
         }
@@ -110,70 +164,94 @@ impl std::fmt::Display for LargeUint {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct GaloisElement {
-    pub elements: [u64; LIMBS],
+pub struct GaloisElement<P: CsidhParams> {
+    pub elements: P::Limbs,
 }
 
-impl GaloisElement {
-    pub fn from_u64(u: u64) -> GaloisElement {
-        let lu = LargeUint {
-            elements: [u, 0, 0, 0, 0, 0, 0, 0]
-        };
-        GaloisElement::from_large_uint(lu)
+impl<P: CsidhParams> GaloisElement<P> {
+    /// The element one
+    pub fn one() -> GaloisElement<P> {
+        GaloisElement {
+            elements: P::R_MOD_P,
+        }
     }
 
-    pub fn from_large_uint(lu: LargeUint) -> GaloisElement {
+    pub fn from_u64(u: u64) -> GaloisElement<P> {
+        GaloisElement::from_large_uint(LargeUint::from_u64(u))
+    }
+
+    pub fn from_large_uint(lu: LargeUint<P>) -> GaloisElement<P> {
         let mut t = GaloisElement {
             elements: lu.elements,
         };
 
-        t.mul_with(&R_SQUARED_MOD_P);
+        t.mul_with(&GaloisElement { elements: P::R_SQUARED_MOD_P });
         return t;
     }
 
-    pub fn into_large_uint(&self) -> LargeUint {
+    pub fn into_large_uint(&self) -> LargeUint<P> {
         let mut s = *self;
-        s.mul_with(&LUINT_1);
+        // Multiplying with a raw 1 divides by R
+        s.mul_with(&GaloisElement { elements: LargeUint::<P>::from_u64(1).elements });
         LargeUint {
             elements: s.elements,
         }
     }
 
-    fn into_large_uint_priv(&self) -> LargeUint {
+    /// Reinterprets a value that is already in Montgomery form, as the reference implementation
+    /// stores its field elements
+    pub fn from_montgomery_uint(lu: LargeUint<P>) -> GaloisElement<P> {
+        GaloisElement {
+            elements: lu.elements,
+        }
+    }
+
+    /// Returns the raw Montgomery form of this element
+    pub fn into_montgomery_uint(&self) -> LargeUint<P> {
+        self.into_large_uint_priv()
+    }
+
+    fn into_large_uint_priv(&self) -> LargeUint<P> {
         LargeUint {
             elements: self.elements,
         }
     }
 
-    pub fn random_element<R: Rng + CryptoRng>(rng: &mut R) -> GaloisElement {
+    pub fn random_element<R: Rng + CryptoRng>(rng: &mut R) -> GaloisElement<P> {
         loop {
-            let mut elems = [0u64; LIMBS];
-            rng.fill(&mut elems);
-            let m = (1u64 << PBITS % 64) - 1;
-            elems[LIMBS - 1] &= m;
+            let mut elems = P::Limbs::filled(0);
+            rng.fill(elems.as_mut());
+            let m = (1u64 << (P::PBITS % 64)) - 1;
+            elems[P::LIMBS - 1] &= m;
 
-            for i in (0..LIMBS).rev() {
-                if elems[i] < P.elements[i] {
+            for i in (0..P::LIMBS).rev() {
+                if elems[i] < P::P[i] {
                     return GaloisElement { elements: elems };
-                } else if elems[i] > P.elements[i] {
+                } else if elems[i] > P::P[i] {
                     break;
                 }
             }
         }
     }
 
-    pub fn sub_from(&mut self, other: &GaloisElement) -> bool {
+    pub fn sub_from(&mut self, other: &GaloisElement<P>) -> bool {
         let mut s = self.into_large_uint_priv();
         let o = other.into_large_uint_priv();
         let r = s.sub_from(&o);
-        if r {
-            s.add_from(&P_INT);
+
+        // Add p back if the subtraction borrowed, masked so that the borrow is not branched on
+        let mask = (r as u64).wrapping_neg();
+        let mut p = LargeUint::<P> { elements: P::P };
+        for i in 0..P::LIMBS {
+            p.elements[i] &= mask;
         }
+        s.add_from(&p);
+
         self.elements = s.elements;
         return r;
     }
 
-    pub fn add_from(&mut self, other: &GaloisElement) -> bool {
+    pub fn add_from(&mut self, other: &GaloisElement<P>) -> bool {
         let mut s = self.into_large_uint_priv();
         let o = other.into_large_uint_priv();
         let r = s.add_from(&o);
@@ -182,22 +260,52 @@ impl GaloisElement {
         return r;
     }
 
-    pub fn mul_with(&mut self, other: &GaloisElement) {
-        let mut temp = [0u64; LIMBS + 1];
+    /// Multiplies in Montgomery form, `self = self * other / R mod p`
+    ///
+    /// On x86_64 with the `bmi2` and `adx` target features, e.g. with `-C target-cpu=native` on
+    /// a recent CPU, this uses MULX, ADCX and ADOX to run the two carry chains of every row in
+    /// parallel, everywhere else the portable implementation.
+    pub fn mul_with(&mut self, other: &GaloisElement<P>) {
+        #[cfg(all(target_arch = "x86_64", target_feature = "bmi2", target_feature = "adx"))]
+        self.mul_with_adx(other);
+
+        #[cfg(not(all(target_arch = "x86_64", target_feature = "bmi2", target_feature = "adx")))]
+        self.mul_with_portable(other);
+    }
+
+    #[cfg(all(target_arch = "x86_64", target_feature = "bmi2", target_feature = "adx"))]
+    fn mul_with_adx(&mut self, other: &GaloisElement<P>) {
+        // Every row adds `a_k * b` and `m * p` to `temp` and shifts it down by one limb. As
+        // `p < R / 2`, `temp` stays below `2 * p` in between and fits into `LIMBS + 1` limbs.
+        let mut temp = P::WideLimbs::filled(0);
+        for k in 0..P::LIMBS {
+            adx::mul_add_row(temp.as_mut(), other.elements.as_ref(), self.elements[k]);
+            let m = P::INV_MIN_P_MOD_R.wrapping_mul(temp[0]);
+            adx::reduce_row(temp.as_mut(), P::P.as_ref(), m);
+        }
+
+        self.elements.as_mut().copy_from_slice(&temp.as_ref()[..P::LIMBS]);
+        self.reduce_once();
+    }
+
+    #[cfg_attr(all(target_arch = "x86_64", target_feature = "bmi2", target_feature = "adx"),
+               allow(dead_code))]
+    fn mul_with_portable(&mut self, other: &GaloisElement<P>) {
+        let mut temp = P::WideLimbs::filled(0);
 
         macro_rules! r {
             ($k:ident, $i:expr) => {
-                ($k + $i) % (LIMBS + 1)
+                ($k + $i) % (P::LIMBS + 1)
             }
         }
 
-        for k in 0..LIMBS {
-            let m: u64 = INV_MIN_P_MOD_R.wrapping_mul(self.elements[k].wrapping_mul(other.elements[0])
+        for k in 0..P::LIMBS {
+            let m: u64 = P::INV_MIN_P_MOD_R.wrapping_mul(self.elements[k].wrapping_mul(other.elements[0])
                                                  .wrapping_add(temp[r!(k, 0)]));
             let mut carry = false;
             let mut other_carry = false;
-            for i in 0..LIMBS {
-                let u: u128 = m as u128 * P.elements[i] as u128;
+            for i in 0..P::LIMBS {
+                let u: u128 = m as u128 * P::P[i] as u128;
 
                 let (res, c) = temp[r!(k,i)].overflowing_add(other_carry as u64);
                 other_carry = c;
@@ -215,11 +323,11 @@ impl GaloisElement {
                 carry |= c;
                 temp[r!(k,i+1)] = res;
             }
-            temp[r!(k,LIMBS)] += other_carry as u64;
+            temp[r!(k,P::LIMBS)] += other_carry as u64;
 
             carry = false;
             other_carry = false;
-            for i in 0..LIMBS {
+            for i in 0..P::LIMBS {
                 let u: u128 = self.elements[k] as u128 * other.elements[i] as u128;
 
                 let (res, c) = temp[r!(k,i)].overflowing_add(other_carry as u64);
@@ -238,25 +346,72 @@ impl GaloisElement {
                 carry |= c;
                 temp[r!(k,i+1)] = res;
             }
-            temp[r!(k,LIMBS)] += other_carry as u64;
+            temp[r!(k,P::LIMBS)] += other_carry as u64;
         }
 
-        for i in 0..LIMBS {
-            self.elements[i] = temp[(LIMBS + i) % (LIMBS + 1)];
+        for i in 0..P::LIMBS {
+            self.elements[i] = temp[(P::LIMBS + i) % (P::LIMBS + 1)];
         }
 
         self.reduce_once();
     }
 
-    pub fn square(&mut self) -> GaloisElement {
-        self.mul_with(&{*self});
+    /// Squares in Montgomery form, `self = self^2 / R mod p`
+    ///
+    /// Every cross product `a_i * a_j` appears twice in a square, so each is computed once and
+    /// doubled. Together with the reduction this takes about a quarter fewer multiplications of
+    /// limbs than `mul_with`.
+    pub fn square(&mut self) -> GaloisElement<P> {
+        let a = self.elements;
+
+        // `2 * a`, with limb `k + 1` replaced by `2 * a_(k + 1)` before row `k`, which makes the
+        // limbs from `k + 1` on `2 * (a_(k + 1) + a_(k + 2) * 2^64 + ...)`
+        let mut doubled = P::WideLimbs::filled(0);
+        for i in 0..P::LIMBS {
+            doubled[i] |= a[i] << 1;
+            doubled[i + 1] = a[i] >> 63;
+        }
+
+        // Like in `mul_with` the window `temp` starts at limb `k` of the product in row `k`. The
+        // square `a_k^2` is needed before the reduction of limb `k`, the cross products of
+        // `a_k` only from limb `2 * k + 1` on, so they are added after the shift. This keeps
+        // `temp` below `3 * p`.
+        let mut temp = P::WideLimbs::filled(0);
+        for k in 0..P::LIMBS {
+            let square = a[k] as u128 * a[k] as u128;
+            let mut carry = 0;
+            for (i, &limb) in [square as u64, (square >> 64) as u64].iter().enumerate() {
+                let (res, c1) = temp[k + i].overflowing_add(limb);
+                let (res, c2) = res.overflowing_add(carry);
+                temp[k + i] = res;
+                carry = (c1 | c2) as u64;
+            }
+            for i in k + 2..=P::LIMBS {
+                let (res, c) = temp[i].overflowing_add(carry);
+                temp[i] = res;
+                carry = c as u64;
+            }
+
+            let m = P::INV_MIN_P_MOD_R.wrapping_mul(temp[0]);
+            rows::reduce_row(temp.as_mut(), P::P.as_ref(), m);
+
+            if k + 1 < P::LIMBS {
+                doubled[k + 1] = a[k + 1] << 1;
+                rows::mul_add_row(&mut temp.as_mut()[k..], &doubled.as_ref()[k + 1..], a[k]);
+            }
+        }
+
+        self.elements.as_mut().copy_from_slice(&temp.as_ref()[..P::LIMBS]);
+        self.reduce_once();
         *self
     }
 
-    fn pow(&mut self, exp: &LargeUint) {
-        let mut prev: GaloisElement = *self;
-        *self = GAL_1;
-        for k in 0..LIMBS {
+    /// Square and multiply, the oracle `inverse` and `is_square` are tested against
+    #[cfg(test)]
+    fn pow(&mut self, exp: &LargeUint<P>) {
+        let mut prev: GaloisElement<P> = *self;
+        *self = GaloisElement::one();
+        for k in 0..P::LIMBS {
             let mut t = exp.elements[k];
             for _ in 0..64 {
                 if (t & 1) != 0 {
@@ -268,39 +423,154 @@ impl GaloisElement {
         }
     }
 
+    /// Inverts in constant time, zero stays zero
     pub fn inverse(&mut self) {
-        self.pow(&P_MINUS_2);
+        // The safegcd inverse of `a * R` is `a^-1 / R`, multiplying by `R^3` in Montgomery form
+        // gives `a^-1 * R`
+        self.elements = inversion::inverse(&self.into_large_uint_priv()).elements;
+        self.mul_with(&Self::r_cubed());
     }
 
+    /// Inverts in variable time, only for public values, zero stays zero
+    pub fn inverse_vartime(&mut self) {
+        self.elements = inversion::inverse_vartime(&self.into_large_uint_priv()).elements;
+        self.mul_with(&Self::r_cubed());
+    }
+
+    /// `R^3 mod p` in Montgomery form
+    fn r_cubed() -> GaloisElement<P> {
+        GaloisElement { elements: P::R_SQUARED_MOD_P }.square()
+    }
+
+    /// Whether `self` is a non-zero square, in constant time
     pub fn is_square(&self) -> bool {
-        let mut t = *self;
-        t.pow(&P_MINUS_1_HALVES);
-        t == GAL_1
+        // `R` is an even power of 2 and so a square, the Montgomery form has the same symbol
+        legendre::is_square(&self.into_large_uint_priv())
+    }
+
+    /// Whether `self` is a non-zero square, in variable time, only for public values
+    pub fn is_square_vartime(&self) -> bool {
+        legendre::is_square_vartime(&self.into_large_uint_priv())
     }
 
+    /// Sets `self` to `other` if `choice` is true, without branching on `choice`
+    pub fn conditional_assign(&mut self, other: &GaloisElement<P>, choice: bool) {
+        let mask = (choice as u64).wrapping_neg();
+        for i in 0..P::LIMBS {
+            self.elements[i] ^= mask & (self.elements[i] ^ other.elements[i]);
+        }
+    }
+
+    /// Swaps `a` and `b` if `choice` is true, without branching on `choice`
+    pub fn conditional_swap(a: &mut GaloisElement<P>, b: &mut GaloisElement<P>, choice: bool) {
+        let mask = (choice as u64).wrapping_neg();
+        for i in 0..P::LIMBS {
+            let t = mask & (a.elements[i] ^ b.elements[i]);
+            a.elements[i] ^= t;
+            b.elements[i] ^= t;
+        }
+    }
+
+    /// Subtracts `p` if `self` is not smaller, without branching on the comparison
     fn reduce_once(&mut self) {
-        let mut temp = self.clone();
-        if !temp.sub_from(&P) {
-            *self = temp;
+        let mut temp = self.into_large_uint_priv();
+        let borrow = temp.sub_from(&LargeUint { elements: P::P });
+        self.conditional_assign(&GaloisElement { elements: temp.elements }, !borrow);
+    }
+}
+
+impl<P: CsidhParams> PrimeField for GaloisElement<P> {
+    type Uint = LargeUint<P>;
+
+    // Zero is its own Montgomery form, there is nothing to multiply
+    fn zero() -> GaloisElement<P> {
+        GaloisElement {
+            elements: P::Limbs::filled(0),
         }
     }
+
+    fn one() -> GaloisElement<P> {
+        GaloisElement::one()
+    }
+
+    fn from_u64(u: u64) -> GaloisElement<P> {
+        GaloisElement::from_u64(u)
+    }
+
+    fn from_uint(u: LargeUint<P>) -> GaloisElement<P> {
+        GaloisElement::from_large_uint(u)
+    }
+
+    fn into_uint(&self) -> LargeUint<P> {
+        self.into_large_uint()
+    }
+
+    fn random_element<R: Rng + CryptoRng>(rng: &mut R) -> GaloisElement<P> {
+        GaloisElement::random_element(rng)
+    }
+
+    fn add_from(&mut self, other: &GaloisElement<P>) {
+        GaloisElement::add_from(self, other);
+    }
+
+    fn sub_from(&mut self, other: &GaloisElement<P>) {
+        GaloisElement::sub_from(self, other);
+    }
+
+    fn mul_with(&mut self, other: &GaloisElement<P>) {
+        GaloisElement::mul_with(self, other)
+    }
+
+    fn square(&mut self) -> GaloisElement<P> {
+        GaloisElement::square(self)
+    }
+
+    fn inverse(&mut self) {
+        GaloisElement::inverse(self)
+    }
+
+    fn inverse_vartime(&mut self) {
+        GaloisElement::inverse_vartime(self)
+    }
+
+    fn is_square(&self) -> bool {
+        GaloisElement::is_square(self)
+    }
+
+    fn is_square_vartime(&self) -> bool {
+        GaloisElement::is_square_vartime(self)
+    }
+
+    fn conditional_assign(&mut self, other: &GaloisElement<P>, choice: bool) {
+        GaloisElement::conditional_assign(self, other, choice)
+    }
+
+    fn conditional_swap(a: &mut GaloisElement<P>, b: &mut GaloisElement<P>, choice: bool) {
+        GaloisElement::conditional_swap(a, b, choice)
+    }
+}
+
+impl<P: CsidhParams> Zeroize for GaloisElement<P> {
+    fn zeroize(&mut self) {
+        self.elements.zeroize();
+    }
 }
 
-impl Add for GaloisElement {
-    type Output = GaloisElement;
+impl<P: CsidhParams> Add for GaloisElement<P> {
+    type Output = GaloisElement<P>;
 
-    fn add(mut self, other: GaloisElement) -> GaloisElement {
+    fn add(mut self, other: GaloisElement<P>) -> GaloisElement<P> {
         self.add_from(&other);
         self
     }
 }
 
-impl std::fmt::Display for GaloisElement {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl<P: CsidhParams> core::fmt::Display for GaloisElement<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         write!(f, "[")?;
-        for i in 0..LIMBS {
+        for i in 0..P::LIMBS {
             write!(f, "0x{:016x}", self.elements[i])?;
-            if i != LIMBS - 1 {
+            if i != P::LIMBS - 1 {
                 write!(f, " ")?;
             }
         }
@@ -308,37 +578,171 @@ impl std::fmt::Display for GaloisElement {
     }
 }
 
-impl Sub for GaloisElement {
-    type Output = GaloisElement;
+impl<P: CsidhParams> Sub for GaloisElement<P> {
+    type Output = GaloisElement<P>;
 
-    fn sub(mut self, other: GaloisElement) -> GaloisElement {
+    fn sub(mut self, other: GaloisElement<P>) -> GaloisElement<P> {
         self.sub_from(&other);
         self
     }
 }
 
-impl Mul for GaloisElement {
-    type Output = GaloisElement;
+impl<P: CsidhParams> Mul for GaloisElement<P> {
+    type Output = GaloisElement<P>;
 
-    fn mul(mut self, other: GaloisElement) -> GaloisElement {
+    fn mul(mut self, other: GaloisElement<P>) -> GaloisElement<P> {
         self.mul_with(&other);
         self
     }
 }
 
-impl Div for GaloisElement {
-    type Output = GaloisElement;
+impl<P: CsidhParams> Div for GaloisElement<P> {
+    type Output = GaloisElement<P>;
 
-    fn div(mut self, mut other: GaloisElement) -> GaloisElement {
-        &other.inverse();
+    fn div(mut self, mut other: GaloisElement<P>) -> GaloisElement<P> {
+        other.inverse();
         self.mul_with(&other);
         self
     }
 }
 
+#[cfg(all(target_arch = "x86_64", target_feature = "bmi2", target_feature = "adx"))]
+use adx as rows;
+#[cfg(not(all(target_arch = "x86_64", target_feature = "bmi2", target_feature = "adx")))]
+use portable as rows;
+
+/// The rows of the Montgomery squaring, `adx` has the same functions in assembly
+#[cfg_attr(all(target_arch = "x86_64", target_feature = "bmi2", target_feature = "adx"),
+           allow(dead_code))]
+mod portable {
+    /// `t += a * b`, `t` has one limb more than `b` and the sum has to fit
+    pub(super) fn mul_add_row(t: &mut [u64], b: &[u64], a: u64) {
+        let mut carry = 0u64;
+        for (t, &b) in t.iter_mut().zip(b) {
+            let s = a as u128 * b as u128 + *t as u128 + carry as u128;
+            *t = s as u64;
+            carry = (s >> 64) as u64;
+        }
+        t[b.len()] = t[b.len()].wrapping_add(carry);
+    }
+
+    /// `t = (t + m * p) / 2^64`, where `m` is chosen so that the division is exact
+    pub(super) fn reduce_row(t: &mut [u64], p: &[u64], m: u64) {
+        let mut carry = 0u64;
+        for i in 0..p.len() {
+            let s = m as u128 * p[i] as u128 + t[i] as u128 + carry as u128;
+            if i > 0 {
+                t[i - 1] = s as u64;
+            }
+            carry = (s >> 64) as u64;
+        }
+        t[p.len() - 1] = t[p.len()].wrapping_add(carry);
+        t[p.len()] = 0;
+    }
+}
+
+/// The rows of the Montgomery multiplication with MULX, ADCX and ADOX
+///
+/// MULX multiplies without touching the flags, so the low halves of the products are added
+/// with the carry flag (ADCX) and the high halves with the overflow flag (ADOX) in the same pass.
+/// The loops count down `rcx` with LEA and JRCXZ, which leave both flags alone as well.
+#[cfg(all(target_arch = "x86_64", target_feature = "bmi2", target_feature = "adx"))]
+mod adx {
+    use core::arch::asm;
+
+    /// `t += a * b`, `t` has one limb more than `b` and the sum has to fit
+    #[inline]
+    pub(super) fn mul_add_row(t: &mut [u64], b: &[u64], a: u64) {
+        assert!(!b.is_empty() && t.len() == b.len() + 1);
+
+        // SAFETY: The loop reads `b.len()` limbs of `b` and reads and writes the
+        // `b.len() + 1` limbs of `t`
+        unsafe {
+            asm!(
+                // Clears both flags
+                "xor {zero:e}, {zero:e}",
+                "2:",
+                "mulx {hi}, {lo}, qword ptr [{b}]",
+                "adcx {lo}, qword ptr [{t}]",
+                "mov qword ptr [{t}], {lo}",
+                "adox {hi}, qword ptr [{t} + 8]",
+                "mov qword ptr [{t} + 8], {hi}",
+                "lea {b}, [{b} + 8]",
+                "lea {t}, [{t} + 8]",
+                "lea rcx, [rcx - 1]",
+                "jrcxz 3f",
+                "jmp 2b",
+                "3:",
+                // The last carry goes into the top limb, the overflow is zero as the sum fits
+                "adcx {zero}, qword ptr [{t}]",
+                "mov qword ptr [{t}], {zero}",
+                b = inout(reg) b.as_ptr() => _,
+                t = inout(reg) t.as_mut_ptr() => _,
+                zero = out(reg) _,
+                hi = out(reg) _,
+                lo = out(reg) _,
+                in("rdx") a,
+                inout("rcx") b.len() => _,
+                options(nostack),
+            );
+        }
+    }
+
+    /// `t = (t + m * p) / 2^64`, where `m` is chosen so that the division is exact
+    #[inline]
+    pub(super) fn reduce_row(t: &mut [u64], p: &[u64], m: u64) {
+        assert!(!p.is_empty() && t.len() == p.len() + 1);
+
+        // SAFETY: The loop reads `p.len()` limbs of `p` and reads and writes the
+        // `p.len() + 1` limbs of `t`
+        unsafe {
+            asm!(
+                "xor {zero:e}, {zero:e}",
+                // The lowest limb becomes zero, only its carry is needed
+                "mulx {hi}, {lo}, qword ptr [{p}]",
+                "adcx {lo}, qword ptr [{t}]",
+                "adox {hi}, qword ptr [{t} + 8]",
+                "mov qword ptr [{t} + 8], {hi}",
+                "lea {p}, [{p} + 8]",
+                "lea {t}, [{t} + 8]",
+                "lea rcx, [rcx - 1]",
+                "jrcxz 3f",
+                // Every other limb is written one limb further down
+                "2:",
+                "mulx {hi}, {lo}, qword ptr [{p}]",
+                "adcx {lo}, qword ptr [{t}]",
+                "mov qword ptr [{t} - 8], {lo}",
+                "adox {hi}, qword ptr [{t} + 8]",
+                "mov qword ptr [{t} + 8], {hi}",
+                "lea {p}, [{p} + 8]",
+                "lea {t}, [{t} + 8]",
+                "lea rcx, [rcx - 1]",
+                "jrcxz 3f",
+                "jmp 2b",
+                "3:",
+                "adcx {zero}, qword ptr [{t}]",
+                "mov qword ptr [{t} - 8], {zero}",
+                "mov qword ptr [{t}], 0",
+                p = inout(reg) p.as_ptr() => _,
+                t = inout(reg) t.as_mut_ptr() => _,
+                zero = out(reg) _,
+                hi = out(reg) _,
+                lo = out(reg) _,
+                in("rdx") m,
+                inout("rcx") p.len() => _,
+                options(nostack),
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::params::{Csidh512, Csidh1024, Csidh1792};
+
+    type LargeUint = super::LargeUint<Csidh512>;
+    type GaloisElement = super::GaloisElement<Csidh512>;
 
     #[test]
     fn check_add() {
@@ -362,7 +766,7 @@ mod test {
         };
 
         let mut max_one = LargeUint {
-            elements: [u64::max_value(), 0, 0, 0, 0, 0, 0, 0]
+            elements: [u64::MAX, 0, 0, 0, 0, 0, 0, 0]
         };
 
         let max_two = LargeUint {
@@ -391,6 +795,25 @@ mod test {
         assert_eq!(one, three);
     }
 
+    #[test]
+    fn check_field_sub() {
+        let mut p_minus_one = LargeUint { elements: Csidh512::P };
+        p_minus_one.sub_from(&LargeUint::from_u64(1));
+        let p_minus_one = GaloisElement { elements: p_minus_one.elements };
+        let mut a = GaloisElement { elements: LargeUint::from_u64(1).elements };
+        assert!(a.sub_from(&GaloisElement { elements: LargeUint::from_u64(2).elements }));
+        assert_eq!(a, p_minus_one);
+        assert!(!a.sub_from(&p_minus_one));
+        assert_eq!(a, GaloisElement { elements: [0; 8] });
+
+        // p - 1 + p - 1 overflows p but not the limbs, reduce_once has to subtract p
+        let mut b = p_minus_one;
+        b.add_from(&p_minus_one);
+        let mut p_minus_two = p_minus_one;
+        p_minus_two.sub_from(&GaloisElement { elements: LargeUint::from_u64(1).elements });
+        assert_eq!(b, p_minus_two);
+    }
+
     #[test]
     fn check_mul() {
         let mut two = GaloisElement::from_u64(2);
@@ -447,6 +870,28 @@ mod test {
         assert!(!one.is_square());
     }
 
+    #[test]
+    fn check_conditional_assign() {
+        let mut two = GaloisElement::from_u64(2);
+        let three = GaloisElement::from_u64(3);
+        two.conditional_assign(&three, false);
+        assert_eq!(two, GaloisElement::from_u64(2));
+        two.conditional_assign(&three, true);
+        assert_eq!(two, three);
+    }
+
+    #[test]
+    fn check_conditional_swap() {
+        let mut two = GaloisElement::from_u64(2);
+        let mut three = GaloisElement::from_u64(3);
+        GaloisElement::conditional_swap(&mut two, &mut three, false);
+        assert_eq!(two, GaloisElement::from_u64(2));
+        assert_eq!(three, GaloisElement::from_u64(3));
+        GaloisElement::conditional_swap(&mut two, &mut three, true);
+        assert_eq!(two, GaloisElement::from_u64(3));
+        assert_eq!(three, GaloisElement::from_u64(2));
+    }
+
     #[test]
     fn check_add_impl() {
         let one = GaloisElement::from_u64(1);
@@ -490,12 +935,38 @@ mod test {
     #[test]
     fn check_parse() {
         let one = LargeUint::from_u64(1);
-        let one_parsed = LargeUint::parse_bytes(b"1");
+        let one_parsed = LargeUint::parse_bytes(b"1").unwrap();
         assert_eq!(one, one_parsed);
 
         let foo = LargeUint::from_u64(12314123);
-        let foo_parsed = LargeUint::parse_bytes(b"12314123");
+        let foo_parsed = LargeUint::parse_bytes(b"12314123").unwrap();
         assert_eq!(foo, foo_parsed);
+
+        assert_eq!(LargeUint::parse_bytes(b"12a3"), Err(CsidhError::InvalidDigit(b'a')));
+        assert_eq!(LargeUint::parse_bytes(b"-1"), Err(CsidhError::InvalidDigit(b'-')));
+    }
+
+    #[test]
+    fn check_bytes() {
+        let foo = LargeUint {
+            elements: [1, 2, 3, 4, 5, 6, 7, u64::MAX]
+        };
+        assert_eq!(LargeUint::from_bytes(&foo.as_bytes()), foo);
+        assert_eq!(foo.as_bytes()[8], 2);
+    }
+
+    #[test]
+    fn check_ord() {
+        let small = LargeUint {
+            elements: [u64::MAX, 0, 0, 0, 0, 0, 0, 1]
+        };
+        let large = LargeUint {
+            elements: [0, 0, 0, 0, 0, 0, 0, 2]
+        };
+        assert!(small < large);
+        let p = LargeUint { elements: Csidh512::P };
+        assert!(LargeUint { elements: Csidh512::P_MINUS_2 } < p);
+        assert_eq!(p.cmp(&p), core::cmp::Ordering::Equal);
     }
 
     #[test]
@@ -509,4 +980,128 @@ mod test {
 
         assert_eq!(one.bits(), 7 * 64 + 2);
     }
+
+    fn check_field<P: CsidhParams>() {
+        let two = super::GaloisElement::<P>::from_u64(2);
+        let three = super::GaloisElement::<P>::from_u64(3);
+        assert_eq!(two * three, super::GaloisElement::from_u64(6));
+        assert_eq!((two - three) + three, two);
+        assert_eq!((three / two) * two, three);
+        assert!(super::GaloisElement::<P>::from_u64(9).is_square());
+
+        // p = 3 mod 4, so -1 is not a square
+        let minus_one = super::GaloisElement::from_u64(0) - super::GaloisElement::<P>::one();
+        assert!(!minus_one.is_square());
+    }
+
+    fn check_inverse_with<P: CsidhParams>() {
+        let mut rng = rand::thread_rng();
+        let minus_one = super::GaloisElement::<P>::from_u64(0) - super::GaloisElement::one();
+        let mut elements = vec![super::GaloisElement::<P>::from_u64(0), super::GaloisElement::one(),
+                                minus_one];
+        elements.extend((0..50).map(|_| super::GaloisElement::<P>::random_element(&mut rng)));
+
+        for a in elements {
+            let mut expected = a;
+            expected.pow(&super::LargeUint { elements: P::P_MINUS_2 });
+            let (mut inverse, mut inverse_vartime) = (a, a);
+            inverse.inverse();
+            inverse_vartime.inverse_vartime();
+            assert_eq!(inverse, expected);
+            assert_eq!(inverse_vartime, expected);
+        }
+    }
+
+    fn check_is_square_with<P: CsidhParams>() {
+        let mut rng = rand::thread_rng();
+        let p = super::LargeUint::<P> { elements: P::P };
+        let mut elements: Vec<super::GaloisElement<P>> = (0..50).map(|k| {
+            let mut x = p;
+            x.sub_from(&super::LargeUint::from_u64(k + 1));
+            super::GaloisElement::from_montgomery_uint(x)
+        }).collect();
+        elements.extend((0..50).map(super::GaloisElement::<P>::from_u64));
+        elements.extend((0..100).map(|_| super::GaloisElement::<P>::random_element(&mut rng)));
+
+        for a in elements {
+            let mut power = a;
+            power.pow(&super::LargeUint { elements: P::P_MINUS_1_HALVES });
+            let expected = power == super::GaloisElement::one();
+            assert_eq!(a.is_square(), expected);
+            assert_eq!(a.is_square_vartime(), expected);
+        }
+    }
+
+    #[test]
+    fn check_is_square_with_pow() {
+        check_is_square_with::<crate::params::Toy>();
+        check_is_square_with::<Csidh512>();
+        check_is_square_with::<Csidh1024>();
+        check_is_square_with::<Csidh1792>();
+    }
+
+    #[test]
+    fn check_inverse_with_pow() {
+        check_inverse_with::<crate::params::Toy>();
+        check_inverse_with::<Csidh512>();
+        check_inverse_with::<Csidh1024>();
+        check_inverse_with::<Csidh1792>();
+    }
+
+    #[test]
+    fn check_larger_fields() {
+        check_field::<Csidh1024>();
+        check_field::<Csidh1792>();
+    }
+
+    fn check_square_with<P: CsidhParams>() {
+        let mut rng = rand::thread_rng();
+        let minus_one = super::GaloisElement::<P>::from_u64(0) - super::GaloisElement::one();
+        let mut all_ones = super::GaloisElement::<P> { elements: P::P_MINUS_1_HALVES };
+        all_ones.add_from(&all_ones.clone());
+        let mut elements = vec![super::GaloisElement::<P>::from_u64(0), super::GaloisElement::one(),
+                                minus_one, all_ones];
+        elements.extend((0..100).map(|_| super::GaloisElement::<P>::random_element(&mut rng)));
+
+        for a in elements {
+            let mut product = a;
+            product.mul_with(&a);
+            assert_eq!(a.clone().square(), product);
+        }
+    }
+
+    #[test]
+    fn check_square_with_mul() {
+        check_square_with::<crate::params::Toy>();
+        check_square_with::<Csidh512>();
+        check_square_with::<Csidh1024>();
+        check_square_with::<Csidh1792>();
+    }
+
+    #[cfg(all(target_arch = "x86_64", target_feature = "bmi2", target_feature = "adx"))]
+    fn check_adx<P: CsidhParams>() {
+        let mut rng = rand::thread_rng();
+        let minus_one = super::GaloisElement::<P>::from_u64(0) - super::GaloisElement::one();
+        let mut elements = vec![super::GaloisElement::<P>::from_u64(0), super::GaloisElement::one(),
+                                minus_one];
+        elements.extend((0..20).map(|_| super::GaloisElement::<P>::random_element(&mut rng)));
+
+        for a in &elements {
+            for b in &elements {
+                let (mut adx, mut portable) = (*a, *a);
+                adx.mul_with_adx(b);
+                portable.mul_with_portable(b);
+                assert_eq!(adx, portable);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "x86_64", target_feature = "bmi2", target_feature = "adx"))]
+    fn check_mul_adx() {
+        check_adx::<crate::params::Toy>();
+        check_adx::<Csidh512>();
+        check_adx::<Csidh1024>();
+        check_adx::<Csidh1792>();
+    }
 }
@@ -0,0 +1,426 @@
+//! Inversion modulo `p` without exponentiation
+//!
+//! `inverse` is the safegcd algorithm of Bernstein and Yang, in the form libsecp256k1 uses. Each
+//! divstep halves `g` after adding or subtracting `f`, swapping the two depending on a counter
+//! `eta`. Only the low bits of `f` and `g` decide the next steps, so 62 of them are done on a
+//! single limb, giving a matrix `t` with `t * [f, g] = 2^62 * [f', g']` that is then applied to
+//! the full numbers. The same matrix moves `d` and `e` along, which keep `d * x = f` and
+//! `e * x = g` modulo `p`, made divisible by `2^62` by adding a multiple of `p`. After a fixed
+//! number of steps `f = ±1` and `d` is the inverse up to sign.
+//!
+//! The numbers are signed with 62 bits per limb, so that a matrix entry times a limb and the
+//! carries fit in an `i128`. All limbs but the top one are in `0..2^62`, the top one carries
+//! the sign.
+//!
+//! `inverse_vartime` is the binary extended Euclidean algorithm, also in batches on single words.
+
+use crate::galois::LargeUint;
+use crate::params::{Array, CsidhParams};
+
+const M62: u64 = u64::MAX >> 2;
+
+/// The transition matrix of 62 divsteps, scaled by `2^62`
+struct Transition {
+    u: i64,
+    v: i64,
+    q: i64,
+    r: i64,
+}
+
+/// Does 62 divsteps on the low limbs of `f` and `g`, returning the new `eta`
+fn divsteps_62(mut eta: i64, mut f: u64, mut g: u64) -> (i64, Transition) {
+    let (mut u, mut v, mut q, mut r) = (1u64, 0u64, 0u64, 1u64);
+
+    for _ in 0..62 {
+        // All ones if `eta < 0`, i.e. if `f` and `g` are swapped when `g` is odd
+        let c1 = (eta >> 63) as u64;
+        // All ones if `g` is odd
+        let c2 = (g & 1).wrapping_neg();
+
+        // `-f` or `f` and the same for its row of the matrix
+        let x = (f ^ c1).wrapping_sub(c1);
+        let y = (u ^ c1).wrapping_sub(c1);
+        let z = (v ^ c1).wrapping_sub(c1);
+
+        g = g.wrapping_add(x & c2);
+        q = q.wrapping_add(y & c2);
+        r = r.wrapping_add(z & c2);
+
+        // With a swap the old `g` becomes `f`, and `eta` turns into `-eta`
+        let c1 = c1 & c2;
+        eta = (eta ^ c1 as i64).wrapping_sub(c1 as i64).wrapping_sub(1);
+        f = f.wrapping_add(g & c1);
+        u = u.wrapping_add(q & c1);
+        v = v.wrapping_add(r & c1);
+
+        g >>= 1;
+        u <<= 1;
+        v <<= 1;
+    }
+
+    (eta, Transition { u: u as i64, v: v as i64, q: q as i64, r: r as i64 })
+}
+
+/// Replaces `d` and `e` with `t * [d, e] / 2^62` modulo `p`
+///
+/// For `d` and `e` in `-2p..p` the results are again in `-2p..p`.
+fn update_de<P: CsidhParams>(d: &mut P::SignedLimbs, e: &mut P::SignedLimbs, t: &Transition,
+                             p: &P::SignedLimbs)
+{
+    let n = <P::SignedLimbs as Array<i64>>::LEN;
+    let (u, v, q, r) = (t.u as i128, t.v as i128, t.q as i128, t.r as i128);
+
+    // Adding `p` once for each negative input keeps the results above `-2p`
+    let (sd, se) = (d[n - 1] >> 63, e[n - 1] >> 63);
+    let mut md = (t.u & sd) + (t.v & se);
+    let mut me = (t.q & sd) + (t.r & se);
+
+    let mut cd = u * d[0] as i128 + v * e[0] as i128;
+    let mut ce = q * d[0] as i128 + r * e[0] as i128;
+
+    // Choose the multiples of `p` so that the low 62 bits of the results vanish
+    let p_inverse = P::INV_MIN_P_MOD_R.wrapping_neg();
+    md -= (p_inverse.wrapping_mul(cd as u64).wrapping_add(md as u64) & M62) as i64;
+    me -= (p_inverse.wrapping_mul(ce as u64).wrapping_add(me as u64) & M62) as i64;
+    cd += p[0] as i128 * md as i128;
+    ce += p[0] as i128 * me as i128;
+    cd >>= 62;
+    ce >>= 62;
+
+    for i in 1..n {
+        cd += u * d[i] as i128 + v * e[i] as i128 + p[i] as i128 * md as i128;
+        ce += q * d[i] as i128 + r * e[i] as i128 + p[i] as i128 * me as i128;
+        d[i - 1] = (cd as u64 & M62) as i64;
+        e[i - 1] = (ce as u64 & M62) as i64;
+        cd >>= 62;
+        ce >>= 62;
+    }
+    d[n - 1] = cd as i64;
+    e[n - 1] = ce as i64;
+}
+
+/// Replaces `f` and `g` with `t * [f, g] / 2^62`, which is exact
+fn update_fg<P: CsidhParams>(f: &mut P::SignedLimbs, g: &mut P::SignedLimbs, t: &Transition) {
+    let n = <P::SignedLimbs as Array<i64>>::LEN;
+    let (u, v, q, r) = (t.u as i128, t.v as i128, t.q as i128, t.r as i128);
+
+    let mut cf = (u * f[0] as i128 + v * g[0] as i128) >> 62;
+    let mut cg = (q * f[0] as i128 + r * g[0] as i128) >> 62;
+
+    for i in 1..n {
+        cf += u * f[i] as i128 + v * g[i] as i128;
+        cg += q * f[i] as i128 + r * g[i] as i128;
+        f[i - 1] = (cf as u64 & M62) as i64;
+        g[i - 1] = (cg as u64 & M62) as i64;
+        cf >>= 62;
+        cg >>= 62;
+    }
+    f[n - 1] = cf as i64;
+    g[n - 1] = cg as i64;
+}
+
+/// Carries the excess of every limb but the top one into the next
+fn propagate<P: CsidhParams>(a: &mut P::SignedLimbs) {
+    let n = <P::SignedLimbs as Array<i64>>::LEN;
+    for i in 0..n - 1 {
+        a[i + 1] += a[i] >> 62;
+        a[i] &= M62 as i64;
+    }
+}
+
+/// Turns `d` in `-2p..p` into `d` or `-d` modulo `p` in `0..p`, negating if `sign` is negative
+fn normalize<P: CsidhParams>(d: &mut P::SignedLimbs, sign: i64, p: &P::SignedLimbs) {
+    let n = <P::SignedLimbs as Array<i64>>::LEN;
+
+    // `-p..p` after adding `p` to a negative `d`, which stays so when negated
+    let add = d[n - 1] >> 63;
+    let negate = sign >> 63;
+    for i in 0..n {
+        d[i] += p[i] & add;
+        d[i] = (d[i] ^ negate) - negate;
+    }
+    propagate::<P>(d);
+
+    let add = d[n - 1] >> 63;
+    for i in 0..n {
+        d[i] += p[i] & add;
+    }
+    propagate::<P>(d);
+}
+
+fn to_signed<P: CsidhParams>(a: &LargeUint<P>) -> P::SignedLimbs {
+    let mut signed = P::SignedLimbs::filled(0);
+    for (i, limb) in signed.as_mut().iter_mut().enumerate() {
+        let (word, shift) = (62 * i / 64, 62 * i % 64);
+        let mut bits = a.elements[word] >> shift;
+        if shift > 2 && word + 1 < P::LIMBS {
+            bits |= a.elements[word + 1] << (64 - shift);
+        }
+        *limb = (bits & M62) as i64;
+    }
+    signed
+}
+
+fn from_signed<P: CsidhParams>(signed: &P::SignedLimbs) -> LargeUint<P> {
+    let mut a = LargeUint::<P>::from_u64(0);
+    for (i, &limb) in signed.as_ref().iter().enumerate() {
+        let (word, shift) = (62 * i / 64, 62 * i % 64);
+        a.elements[word] |= (limb as u64) << shift;
+        if shift > 2 && word + 1 < P::LIMBS {
+            a.elements[word + 1] |= limb as u64 >> (64 - shift);
+        }
+    }
+    a
+}
+
+/// An upper bound on the divsteps needed for numbers below `2^P::PBITS`, in batches of 62
+///
+/// This is theorem 11.2 of the safegcd paper.
+fn batches<P: CsidhParams>() -> u64 {
+    let d = P::PBITS;
+    let steps = if d < 46 { (49 * d + 80) / 17 } else { (49 * d + 57) / 17 };
+    steps.div_ceil(62)
+}
+
+/// Computes `x^-1 mod p` for `x` in `0..p` in constant time, zero stays zero
+pub(crate) fn inverse<P: CsidhParams>(x: &LargeUint<P>) -> LargeUint<P> {
+    let n = <P::SignedLimbs as Array<i64>>::LEN;
+    let p = to_signed::<P>(&LargeUint { elements: P::P });
+
+    let (mut f, mut g) = (p, to_signed::<P>(x));
+    let (mut d, mut e) = (P::SignedLimbs::filled(0), P::SignedLimbs::filled(0));
+    e[0] = 1;
+
+    let mut eta = -1;
+    for _ in 0..batches::<P>() {
+        let (next, t) = divsteps_62(eta, f[0] as u64, g[0] as u64);
+        eta = next;
+        update_de::<P>(&mut d, &mut e, &t, &p);
+        update_fg::<P>(&mut f, &mut g, &t);
+    }
+
+    // `f` is the gcd up to sign, so `-1` or `1`
+    normalize::<P>(&mut d, f[n - 1], &p);
+    from_signed::<P>(&d)
+}
+
+/// The number of steps in a batch of the binary GCD
+const BINARY_STEPS: u32 = 31;
+
+/// The matrix of a batch of binary GCD steps, scaled by `2^steps`
+pub(crate) struct BinaryTransition {
+    pub(crate) f0: i64,
+    pub(crate) g0: i64,
+    pub(crate) f1: i64,
+    pub(crate) g1: i64,
+}
+
+/// Does a batch of `steps` binary GCD steps on the approximations of `a` and `b`, `steps` at most
+/// 31
+///
+/// A step halves `a`, after subtracting `b` if `a` is odd, swapping the two first if `a < b`. Bit 0
+/// of the first result is the parity of the steps that flip the sign of the Jacobi symbol
+/// `(a / b)`: halving if `b = 3, 5 mod 8` and swapping if `a = b = 3 mod 4`.
+pub(crate) fn binary_steps(mut a: u64, mut b: u64, steps: u32) -> (u64, BinaryTransition) {
+    let (mut f0, mut g0, mut f1, mut g1) = (1u64, 0u64, 0u64, 1u64);
+    let mut flips = 0;
+
+    for _ in 0..steps {
+        let odd = (a & 1).wrapping_neg();
+        let swap = odd & (a.overflowing_sub(b).1 as u64).wrapping_neg();
+
+        flips ^= swap & ((a & b) >> 1);
+        let t = swap & (a ^ b);
+        a ^= t;
+        b ^= t;
+        let t = swap & (f0 ^ f1);
+        f0 ^= t;
+        f1 ^= t;
+        let t = swap & (g0 ^ g1);
+        g0 ^= t;
+        g1 ^= t;
+
+        a = a.wrapping_sub(odd & b);
+        f0 = f0.wrapping_sub(odd & f1);
+        g0 = g0.wrapping_sub(odd & g1);
+
+        a >>= 1;
+        f1 <<= 1;
+        g1 <<= 1;
+        flips ^= (b >> 1) ^ (b >> 2);
+    }
+
+    (flips, BinaryTransition { f0: f0 as i64, g0: g0 as i64, f1: f1 as i64, g1: g1 as i64 })
+}
+
+/// The words standing in for `a` and `b` in `binary_steps`
+///
+/// These are the top 33 bits of both from the top bit of the larger one on, next to their low
+/// 31 bits. While both fit in a word they are exact.
+pub(crate) fn approximate<P: CsidhParams>(a: &LargeUint<P>, b: &LargeUint<P>) -> (u64, u64) {
+    let (a, b) = (&a.elements, &b.elements);
+
+    let (mut a_top, mut b_top) = (a[0], b[0]);
+    for i in 1..P::LIMBS {
+        let c = a[i] | b[i];
+        let nonzero = ((c | c.wrapping_neg()) >> 63).wrapping_neg();
+        let s = c.leading_zeros() & 63;
+
+        // `x << s | y >> (64 - s)`, without shifting by 64 for `s = 0`
+        let a_window = (a[i] << s) | ((a[i - 1] >> 1) >> (63 - s));
+        let b_window = (b[i] << s) | ((b[i - 1] >> 1) >> (63 - s));
+        a_top = (a_top & !nonzero) | (a_window & nonzero);
+        b_top = (b_top & !nonzero) | (b_window & nonzero);
+    }
+
+    let low = (1 << 31) - 1;
+    ((a_top & !low) | (a[0] & low), (b_top & !low) | (b[0] & low))
+}
+
+/// Computes `(f * a + g * b + m * p) / 2^steps` and the word above it, with `m = 0` if `a` and `b`
+/// are the numbers of the GCD and otherwise the `m` that makes the division exact
+pub(crate) fn combine<P: CsidhParams>(a: &LargeUint<P>, b: &LargeUint<P>, f: i64, g: i64, m: u64,
+                                      steps: u32)
+    -> (LargeUint<P>, i64)
+{
+    let mut sum = LargeUint::<P>::from_u64(0);
+    let mut acc: i128 = 0;
+    for i in 0..P::LIMBS {
+        acc += f as i128 * a.elements[i] as i128 + g as i128 * b.elements[i] as i128
+            + m as i128 * P::P[i] as i128;
+        sum.elements[i] = acc as u64;
+        acc >>= 64;
+    }
+
+    for i in 0..P::LIMBS - 1 {
+        sum.elements[i] = (sum.elements[i] >> steps) | (sum.elements[i + 1] << (64 - steps));
+    }
+    sum.elements[P::LIMBS - 1] = (sum.elements[P::LIMBS - 1] >> steps) | ((acc as u64) << (64 - steps));
+    (sum, (acc >> steps) as i64)
+}
+
+/// Computes `(f * u + g * v) / 2^BINARY_STEPS mod p` for `u` and `v` in `0..p`
+fn combine_mod<P: CsidhParams>(u: &LargeUint<P>, v: &LargeUint<P>, f: i64, g: i64) -> LargeUint<P> {
+    let low = (f as u64).wrapping_mul(u.elements[0]).wrapping_add((g as u64).wrapping_mul(v.elements[0]));
+    let m = low.wrapping_mul(P::INV_MIN_P_MOD_R) & ((1 << BINARY_STEPS) - 1);
+
+    // `|f| + |g| <= 2^BINARY_STEPS`, so the result is in `-p..2p`
+    let (mut sum, top) = combine(u, v, f, g, m, BINARY_STEPS);
+    let p = LargeUint { elements: P::P };
+    if top < 0 {
+        sum.add_from(&p);
+    } else if top > 0 || sum >= p {
+        sum.sub_from(&p);
+    }
+    sum
+}
+
+/// Computes `x^-1 mod p` for `x` in `0..p`, zero stays zero
+///
+/// This is the binary GCD with the optimizations of Pornin's "Optimized Binary GCD for Modular
+/// Inversion": batches of steps are done on words approximating `a` and `b` and give a matrix,
+/// which is applied to the full numbers. Unlike `inverse` it stops once `a = 0` instead of after
+/// the worst case number of batches, so the running time depends on `x` and this is only for
+/// public values.
+pub(crate) fn inverse_vartime<P: CsidhParams>(x: &LargeUint<P>) -> LargeUint<P> {
+    let zero = LargeUint::from_u64(0);
+    let p = LargeUint::<P> { elements: P::P };
+
+    // `u * x = a` and `v * x = b` modulo `p`
+    let (mut a, mut b) = (*x, p);
+    let (mut u, mut v) = (LargeUint::from_u64(1), zero);
+    while a != zero {
+        let (approximate_a, approximate_b) = approximate(&a, &b);
+        let (_, t) = binary_steps(approximate_a, approximate_b, BINARY_STEPS);
+
+        // A wrong comparison of the approximations can make `a` or `b` negative
+        let ((next_a, _), (next_b, _)) = (combine(&a, &b, t.f0, t.g0, 0, BINARY_STEPS),
+                                          combine(&a, &b, t.f1, t.g1, 0, BINARY_STEPS));
+        let (next_u, next_v) = (combine_mod(&u, &v, t.f0, t.g0), combine_mod(&u, &v, t.f1, t.g1));
+        (a, b, u, v) = (next_a, next_b, next_u, next_v);
+        if a.elements[P::LIMBS - 1] >> 63 == 1 {
+            a = negate(&a);
+            u = negate_mod(&u, &p);
+        }
+        if b.elements[P::LIMBS - 1] >> 63 == 1 {
+            b = negate(&b);
+            v = negate_mod(&v, &p);
+        }
+    }
+
+    // `b` is the gcd of `x` and `p`, so 1 unless `x` is zero
+    if b == LargeUint::from_u64(1) { v } else { zero }
+}
+
+pub(crate) fn negate<P: CsidhParams>(x: &LargeUint<P>) -> LargeUint<P> {
+    let mut negated = LargeUint::from_u64(0);
+    negated.sub_from(x);
+    negated
+}
+
+fn negate_mod<P: CsidhParams>(x: &LargeUint<P>, p: &LargeUint<P>) -> LargeUint<P> {
+    if *x == LargeUint::from_u64(0) {
+        return *x;
+    }
+    let mut negated = *p;
+    negated.sub_from(x);
+    negated
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::galois::GaloisElement;
+    use crate::params::{Csidh512, Csidh1024, Csidh1792, Toy};
+
+    fn check_inverse_with<P: CsidhParams>() {
+        let mut rng = rand::thread_rng();
+        let p = LargeUint::<P> { elements: P::P };
+
+        // Close to `p` the approximations of the binary GCD agree in their top bits
+        let mut values: Vec<LargeUint<P>> = (1..50).map(|k| {
+            let mut x = p;
+            x.sub_from(&LargeUint::from_u64(k));
+            x
+        }).collect();
+        values.extend((1..50).map(LargeUint::from_u64));
+        values.extend((0..50).map(|_| GaloisElement::<P>::random_element(&mut rng).into_large_uint()));
+
+        for x in values {
+            let y = inverse(&x);
+            assert_eq!(inverse_vartime(&x), y);
+            assert_eq!(GaloisElement::from_large_uint(x) * GaloisElement::from_large_uint(y),
+                       GaloisElement::one());
+        }
+
+        let zero = LargeUint::from_u64(0);
+        assert_eq!(inverse::<P>(&zero), zero);
+        assert_eq!(inverse_vartime::<P>(&zero), zero);
+    }
+
+    #[test]
+    fn check_inverse() {
+        check_inverse_with::<Csidh512>();
+        check_inverse_with::<Csidh1024>();
+        check_inverse_with::<Csidh1792>();
+    }
+
+    #[test]
+    fn check_inverse_toy() {
+        let p = Toy::P[0];
+        for x in 1..p {
+            let x = LargeUint::<Toy>::from_u64(x);
+            let y = inverse(&x);
+            assert_eq!(y, inverse_vartime(&x));
+            assert_eq!((x.elements[0] as u128 * y.elements[0] as u128) % p as u128, 1);
+        }
+    }
+
+    #[test]
+    fn check_signed_limbs() {
+        let p = LargeUint::<Csidh512> { elements: Csidh512::P };
+        let signed = to_signed(&p);
+        assert!(signed.iter().all(|&limb| (0..1 << 62).contains(&limb)));
+        assert_eq!(from_signed(&signed), p);
+    }
+}
@@ -0,0 +1,219 @@
+use rand::prelude::*;
+
+use crate::error::CsidhError;
+use crate::params::{Array, CsidhParams};
+
+/// The set private keys are sampled from, one range of exponents per prime
+///
+/// The default is `-MAX_EXPONENT..=MAX_EXPONENT` for every prime, as in the CSIDH paper. Smaller
+/// ranges for the large primes, like the weighted bounds of Meyer and Reith, make the group action
+/// faster at the cost of fewer keys. The ranges are public, the constant time action does as many
+/// isogenies per prime as the largest absolute exponent of its range.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// # use csidh::{CsidhPrivateKey, KeySpace};
+/// # let mut rng: rand::rngs::ThreadRng = unimplemented!();
+/// let key_space = KeySpace::ternary();
+/// assert!(key_space.bits() > 117.0);
+/// let private = CsidhPrivateKey::generate_new(&key_space, &mut rng);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySpace<P: CsidhParams> {
+    lower: P::Exponents,
+    upper: P::Exponents,
+}
+
+impl<P: CsidhParams> Default for KeySpace<P> {
+    fn default() -> KeySpace<P> {
+        KeySpace::uniform(P::MAX_EXPONENT).expect("MAX_EXPONENT is a valid bound")
+    }
+}
+
+impl<P: CsidhParams> KeySpace<P> {
+    /// Exponents in `lower[i]..=upper[i]` for the `i`-th prime
+    ///
+    /// Every range has to be nonempty and the exponents have to be larger than `-128`.
+    pub fn from_ranges(lower: &[i8], upper: &[i8]) -> Result<KeySpace<P>, CsidhError> {
+        for bounds in [lower, upper] {
+            if bounds.len() != P::NUM_PRIMES {
+                return Err(CsidhError::InvalidLength {
+                    expected: P::NUM_PRIMES,
+                    actual: bounds.len(),
+                });
+            }
+        }
+
+        let mut key_space = KeySpace::<P> {
+            lower: P::Exponents::filled(0),
+            upper: P::Exponents::filled(0),
+        };
+
+        for i in 0..P::NUM_PRIMES {
+            if lower[i] > upper[i] || lower[i] == i8::MIN {
+                return Err(CsidhError::InvalidBounds { index: i });
+            }
+            key_space.lower[i] = lower[i];
+            key_space.upper[i] = upper[i];
+        }
+
+        Ok(key_space)
+    }
+
+    /// Exponents in `-bounds[i]..=bounds[i]` for the `i`-th prime
+    pub fn from_bounds(bounds: &[i8]) -> Result<KeySpace<P>, CsidhError> {
+        if bounds.len() != P::NUM_PRIMES {
+            return Err(CsidhError::InvalidLength {
+                expected: P::NUM_PRIMES,
+                actual: bounds.len(),
+            });
+        }
+
+        let mut lower = P::Exponents::filled(0);
+        for i in 0..P::NUM_PRIMES {
+            lower[i] = bounds[i].checked_neg().unwrap_or(i8::MIN);
+        }
+
+        KeySpace::from_ranges(lower.as_ref(), bounds)
+    }
+
+    /// Exponents in `-bound..=bound` for every prime
+    pub fn uniform(bound: i8) -> Result<KeySpace<P>, CsidhError> {
+        KeySpace::from_bounds(P::Exponents::filled(bound).as_ref())
+    }
+
+    /// Exponents in `0..=bound` for every prime
+    pub fn nonnegative(bound: i8) -> Result<KeySpace<P>, CsidhError> {
+        KeySpace::from_ranges(P::Exponents::filled(0).as_ref(), P::Exponents::filled(bound).as_ref())
+    }
+
+    /// Exponents in `-1..=1` for every prime
+    pub fn ternary() -> KeySpace<P> {
+        KeySpace::uniform(1).expect("1 is a valid bound")
+    }
+
+    /// The smallest exponent of each prime
+    pub fn lower(&self) -> &[i8] {
+        self.lower.as_ref()
+    }
+
+    /// The largest exponent of each prime
+    pub fn upper(&self) -> &[i8] {
+        self.upper.as_ref()
+    }
+
+    /// Whether every exponent is within the range of its prime
+    pub fn contains(&self, exponents: &[i8]) -> bool {
+        exponents.len() == P::NUM_PRIMES
+            && (0..P::NUM_PRIMES).all(|i| (self.lower[i]..=self.upper[i]).contains(&exponents[i]))
+    }
+
+    /// The base 2 logarithm of the number of private keys
+    ///
+    /// Different exponent vectors may still give the same public key, the class group has about
+    /// `sqrt(p)` elements.
+    pub fn bits(&self) -> f64 {
+        (0..P::NUM_PRIMES)
+            .map(|i| log2((self.upper[i] as i32 - self.lower[i] as i32 + 1) as u64))
+            .sum()
+    }
+
+    /// The largest absolute exponent of each prime
+    pub(crate) fn max_isogenies(&self) -> P::Exponents {
+        let mut max = P::Exponents::filled(0);
+        for i in 0..P::NUM_PRIMES {
+            max[i] = self.lower[i].abs().max(self.upper[i].abs());
+        }
+        max
+    }
+
+    /// Samples an exponent vector uniformly from the key space into `exponents`
+    pub(crate) fn sample<R: CryptoRng + Rng>(&self, exponents: &mut P::Exponents, rng: &mut R) {
+        use rand::distributions::{Distribution, Uniform};
+
+        for i in 0..P::NUM_PRIMES {
+            exponents[i] = Uniform::from(self.lower[i]..=self.upper[i]).sample(rng);
+        }
+    }
+}
+
+/// The base 2 logarithm of `x`, which has to be positive
+///
+/// `f64::log2` needs the standard library, this splits off the exponent and sums up the series
+/// `ln(m) = 2 * (t + t^3 / 3 + t^5 / 5 + ...)` with `t = (m - 1) / (m + 1)` for the rest.
+pub(crate) fn log2(x: u64) -> f64 {
+    let exponent = 63 - x.leading_zeros();
+    let m = x as f64 / (1u64 << exponent) as f64;
+
+    let t = (m - 1.0) / (m + 1.0);
+    let mut ln = 0.0;
+    let mut power = t;
+    for k in 0..20 {
+        ln += power / (2 * k + 1) as f64;
+        power *= t * t;
+    }
+
+    exponent as f64 + 2.0 * ln / core::f64::consts::LN_2
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::{Csidh512, Csidh1792};
+
+    type KeySpace = super::KeySpace<Csidh512>;
+
+    #[test]
+    fn check_log2() {
+        for &x in [1, 2, 3, 11, 231, 1 << 40, (1 << 40) - 1, u64::MAX].iter() {
+            assert!((log2(x) - (x as f64).log2()).abs() < 1e-12, "{}", x);
+        }
+    }
+
+    #[test]
+    fn check_bits() {
+        assert!((KeySpace::default().bits() - 74.0 * 11f64.log2()).abs() < 1e-9);
+        assert!((KeySpace::ternary().bits() - 74.0 * 3f64.log2()).abs() < 1e-9);
+        assert_eq!(KeySpace::nonnegative(10).unwrap().bits(), KeySpace::default().bits());
+        assert_eq!(KeySpace::uniform(0).unwrap().bits(), 0.0);
+        assert!(super::KeySpace::<Csidh1792>::default().bits() > 256.0);
+
+        let mut bounds = [5i8; 74];
+        bounds[73] = 0;
+        assert!((KeySpace::from_bounds(&bounds).unwrap().bits() - 73.0 * 11f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn check_invalid() {
+        assert_eq!(KeySpace::uniform(-1), Err(CsidhError::InvalidBounds { index: 0 }));
+        assert_eq!(KeySpace::uniform(i8::MIN), Err(CsidhError::InvalidBounds { index: 0 }));
+        assert_eq!(KeySpace::from_bounds(&[1; 73]),
+                   Err(CsidhError::InvalidLength { expected: 74, actual: 73 }));
+
+        let mut upper = [1i8; 74];
+        upper[3] = -1;
+        assert_eq!(KeySpace::from_ranges(&[0; 74], &upper),
+                   Err(CsidhError::InvalidBounds { index: 3 }));
+    }
+
+    #[test]
+    fn check_sample() {
+        let mut rng = thread_rng();
+        let mut upper = [3i8; 74];
+        upper[0] = 127;
+        let mut lower = [-1i8; 74];
+        lower[0] = 120;
+        let key_space = KeySpace::from_ranges(&lower, &upper).unwrap();
+        assert_eq!(key_space.max_isogenies()[0], 127);
+        assert_eq!(key_space.max_isogenies()[1], 3);
+
+        let mut exponents = [0i8; 74];
+        for _ in 0..100 {
+            key_space.sample(&mut exponents, &mut rng);
+            assert!(key_space.contains(&exponents));
+        }
+        assert!(!key_space.contains(&[0; 74]));
+        assert!(!key_space.contains(&exponents[1..]));
+    }
+}
@@ -0,0 +1,147 @@
+//! Legendre symbols modulo `p` without exponentiation
+//!
+//! Both functions run the batched binary GCD of `inverse_vartime` on `x` and `p`, tracking the
+//! Jacobi symbol `(a / b)` along the way. Halving `a` multiplies it by `(2 / b)` and swapping by
+//! `-1` if `a = b = 3 mod 4`, which only needs the low 3 bits of `a` and `b`. The approximations
+//! have 31 exact low bits and each step loses one, so a batch is 29 steps here.
+//!
+//! A wrong comparison of the approximations can make `a` or `b` negative. Starting from
+//! non-negative numbers they can never both be, so quadratic reciprocity still holds in the form
+//! above. Negating `b` at the end of a batch keeps `(a / |b|)`, negating `a` multiplies it by
+//! `(-1 / |b|)`.
+//!
+//! This is the binary GCD and not the divsteps `inverse` uses, as reciprocity needs the signs of
+//! both numbers whenever they are swapped. Divsteps let `f` and `g` both turn negative and their
+//! signs are not known from the low bits, libsecp256k1 sidesteps this with a variant of divsteps
+//! that keeps both non-negative, but without a bound on its number of steps and in variable time.
+//! The binary GCD has such a bound: Pornin, "Optimized Binary GCD for Modular Inversion" (ePrint
+//! 2020/972), shows that every step shrinks `len(a) + len(b)` by at least one, also with the
+//! approximations for batches of up to 31 steps, so `2 * PBITS - 1` steps always reach `a = 0`.
+//! Batches of 29 steps are shorter than the approximations allow and keep that bound.
+
+use crate::galois::LargeUint;
+use crate::inversion::{approximate, binary_steps, combine, negate};
+use crate::params::CsidhParams;
+
+/// The number of steps in a batch
+const STEPS: u32 = 29;
+
+/// Negates `x` if it is negative in two's complement, returning all ones if it was
+fn negate_if_negative<P: CsidhParams>(x: &mut LargeUint<P>) -> u64 {
+    let mask = (x.elements[P::LIMBS - 1] >> 63).wrapping_neg();
+    let mut carry = mask & 1;
+    for limb in x.elements.as_mut() {
+        let (res, c) = (*limb ^ mask).overflowing_add(carry);
+        *limb = res;
+        carry = c as u64;
+    }
+    mask
+}
+
+/// The number of batches for numbers below `2^P::PBITS`
+///
+/// Every batch shrinks the sum of the bit lengths of `a` and `b` by at least the number of steps,
+/// which is `2 * PBITS` at the start and 1 once `a = 0` and `b = 1`.
+fn batches<P: CsidhParams>() -> u64 {
+    (2 * P::PBITS - 1).div_ceil(STEPS as u64)
+}
+
+/// Whether `x` in `0..p` is a non-zero square modulo `p`, in constant time
+pub(crate) fn is_square<P: CsidhParams>(x: &LargeUint<P>) -> bool {
+    let (mut a, mut b) = (*x, LargeUint { elements: P::P });
+    let mut flips = 0;
+
+    for _ in 0..batches::<P>() {
+        let (approximate_a, approximate_b) = approximate(&a, &b);
+        let (batch_flips, t) = binary_steps(approximate_a, approximate_b, STEPS);
+        flips ^= batch_flips;
+
+        let ((next_a, _), (next_b, _)) = (combine(&a, &b, t.f0, t.g0, 0, STEPS),
+                                          combine(&a, &b, t.f1, t.g1, 0, STEPS));
+        a = next_a;
+        b = next_b;
+        negate_if_negative(&mut b);
+        let negated = negate_if_negative(&mut a);
+        flips ^= negated & (b.elements[0] >> 1);
+    }
+
+    // `b` is the gcd of `x` and `p`, so 1 unless `x` is zero
+    let not_one = b.elements.as_ref().iter().skip(1).fold(b.elements[0] ^ 1, |acc, &limb| acc | limb);
+    (flips & 1 == 0) & (not_one == 0)
+}
+
+/// Whether `x` in `0..p` is a non-zero square modulo `p`
+///
+/// This stops once `a = 0`, so the running time depends on `x` and this is only for public
+/// values.
+pub(crate) fn is_square_vartime<P: CsidhParams>(x: &LargeUint<P>) -> bool {
+    let zero = LargeUint::from_u64(0);
+    let (mut a, mut b) = (*x, LargeUint::<P> { elements: P::P });
+    let mut flips = 0;
+
+    while a != zero {
+        let (approximate_a, approximate_b) = approximate(&a, &b);
+        let (batch_flips, t) = binary_steps(approximate_a, approximate_b, STEPS);
+        flips ^= batch_flips;
+
+        let ((next_a, _), (next_b, _)) = (combine(&a, &b, t.f0, t.g0, 0, STEPS),
+                                          combine(&a, &b, t.f1, t.g1, 0, STEPS));
+        (a, b) = (next_a, next_b);
+        if b.elements[P::LIMBS - 1] >> 63 == 1 {
+            b = negate(&b);
+        }
+        if a.elements[P::LIMBS - 1] >> 63 == 1 {
+            a = negate(&a);
+            flips ^= b.elements[0] >> 1;
+        }
+    }
+
+    flips & 1 == 0 && b == LargeUint::from_u64(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::galois::GaloisElement;
+    use crate::params::{Csidh512, Csidh1024, Csidh1792, Toy};
+
+    fn check_is_square_with<P: CsidhParams>() {
+        let mut rng = rand::thread_rng();
+        let p = LargeUint::<P> { elements: P::P };
+
+        // Close to `p` the top bits of `a` and `b` agree in the first batch
+        let mut values: Vec<LargeUint<P>> = (1..100).map(|k| {
+            let mut x = p;
+            x.sub_from(&LargeUint::from_u64(k));
+            x
+        }).collect();
+        values.extend((0..100).map(LargeUint::from_u64));
+        values.extend((0..100).map(|_| GaloisElement::<P>::random_element(&mut rng).into_large_uint()));
+
+        for x in values {
+            assert_eq!(is_square(&x), is_square_vartime(&x));
+        }
+    }
+
+    #[test]
+    fn check_is_square() {
+        check_is_square_with::<Csidh512>();
+        check_is_square_with::<Csidh1024>();
+        check_is_square_with::<Csidh1792>();
+    }
+
+    #[test]
+    fn check_is_square_toy() {
+        let p = Toy::P[0];
+        let mut squares = vec![false; p as usize];
+        for y in 1..p {
+            squares[(y * y % p) as usize] = true;
+        }
+
+        for x in 0..p {
+            let x_uint = LargeUint::<Toy>::from_u64(x);
+            assert_eq!(is_square(&x_uint), squares[x as usize]);
+            assert_eq!(is_square_vartime(&x_uint), squares[x as usize]);
+        }
+    }
+}
@@ -9,25 +9,123 @@
 //! following snippet shows the basic gist of it.
 //!
 //! ```rust,no_run
-//! # use csidh::CsidhPrivateKey;
+//! # use csidh::{CsidhPrivateKey, KeySpace};
 //! let mut rng = rand::thread_rng();
 //!
-//! let a_private = CsidhPrivateKey::generate_new(&mut rng);
-//! let b_private = CsidhPrivateKey::generate_new(&mut rng);
+//! let a_private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
+//! let b_private = CsidhPrivateKey::generate_new(&KeySpace::default(), &mut rng);
 //!
 //! let a_public = a_private.get_public_key();
 //! let b_public = b_private.get_public_key();
 //!
-//! let a_shared = a_private.get_shared_secret(&b_public);
-//! let b_shared = b_private.get_shared_secret(&a_public);
+//! let a_shared = a_private.get_shared_secret(&b_public).unwrap();
+//! let b_shared = b_private.get_shared_secret(&a_public).unwrap();
 //!
 //! assert_eq!(a_shared, b_shared);
+//!
+//! // The shared curve is not uniformly random, hash it into a key before using it
+//! let a_key = a_shared.derive_key(&a_public, &b_public, b"example");
+//! let b_key = b_shared.derive_key(&b_public, &a_public, b"example");
+//! assert_eq!(a_key, b_key);
 //! ```
 //!
+//! ## Parameter sets
+//!
+//! `CsidhPrivateKey` and `CsidhPublicKey` use the original CSIDH-512 parameters. The larger
+//! CSIDH-1024 and CSIDH-1792 sets are selected with the type parameter of `PrivateKey` and
+//! `PublicKey`:
+//!
+//! ```rust,no_run
+//! # use csidh::{Csidh1024, KeySpace, PrivateKey};
+//! let mut rng = rand::thread_rng();
+//!
+//! let private = PrivateKey::<Csidh1024>::generate_new(&KeySpace::default(), &mut rng);
+//! let public = private.get_public_key();
+//! ```
+//!
+//! Protocol code that should work with any key exchange can use the `Nike` trait instead, which
+//! every parameter set implements.
+//!
+//! ## CTIDH
+//!
+//! `CtidhPrivateKey` draws its keys from a `CtidhKeySpace`, which bounds the exponents per batch
+//! of primes instead of per prime, and always uses a constant time action. Its public keys are
+//! ordinary `PublicKey`s, so both kinds of keys can be mixed in a key exchange.
+//!
+//! ```rust,no_run
+//! # use csidh::{CtidhKeySpace, CtidhPrivateKey};
+//! let mut rng = rand::thread_rng();
+//!
+//! let private: CtidhPrivateKey = CtidhPrivateKey::generate_new(&CtidhKeySpace::default(), &mut rng);
+//! let public = private.get_public_key();
+//! ```
+//!
+//! ## Signatures
+//!
+//! `CsiFish` signs messages with the group action. It needs the structure of the class group,
+//! for CSIDH-512 the class number is built into `ClassGroup::csidh512`, which takes the reduced
+//! basis of the relation lattice computed by the CSI-FiSh authors. `SeaSign` gets by without the
+//! class group by rejection sampling the exponent vectors, at the cost of much larger signatures
+//! and many aborted attempts.
+//!
+//! ## `no_std`
+//!
+//! Without the default `std` feature the crate is `#![no_std]` and gets by without an allocator,
+//! so that key exchanges can run on microcontrollers and in enclaves. Keys, public keys and
+//! shared secrets are fixed-size arrays, and the randomness has to come from the caller through
+//! the `_with_rng` methods:
+//!
+//! ```rust,no_run
+//! # use csidh::{CsidhPrivateKey, KeySpace};
+//! # fn exchange<R: rand::CryptoRng + rand::Rng>(rng: &mut R) {
+//! let private = CsidhPrivateKey::generate_new(&KeySpace::default(), rng);
+//! let public = private.get_public_key_with_rng(rng);
+//! let shared = private.get_shared_secret_with_rng(&public, rng).unwrap();
+//! # }
+//! ```
+//!
+//! The isogenies are always computed with Vélu's formulas then, √élu, the signatures and the
+//! `Nike` trait need `std`.
+//!
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::needless_return, clippy::needless_range_loop, clippy::wrong_self_convention)]
 
-mod global;
+pub mod params;
+#[cfg(feature = "std")]
+mod classgroup;
+mod edwards;
+mod field;
 mod galois;
+mod inversion;
+mod keyspace;
+mod legendre;
 mod csidh;
+#[cfg(feature = "std")]
+mod csifish;
+mod ctidh;
+mod error;
 mod montgomery;
+#[cfg(feature = "std")]
+mod nike;
+#[cfg(feature = "std")]
+mod seasign;
+#[cfg(feature = "std")]
+mod sqrt_velu;
 
-pub use crate::csidh::{CsidhPrivateKey, CsidhPublicKey};
+#[cfg(feature = "std")]
+pub use crate::classgroup::ClassGroup;
+#[cfg(feature = "std")]
+pub use crate::csifish::{CsiFish, CsiFishPrivateKey, CsiFishPublicKey, CsiFishSignature};
+pub use crate::csidh::{
+    ActionMode, CsidhPrivateKey, CsidhPublicKey, CsidhSharedKey, CsidhSharedSecret, CurveModel,
+    PrivateKey, PublicKey,
+};
+pub use crate::ctidh::{CtidhKeySpace, CtidhPrivateKey};
+pub use crate::error::CsidhError;
+pub use crate::keyspace::KeySpace;
+#[cfg(feature = "std")]
+pub use crate::nike::Nike;
+#[cfg(feature = "std")]
+pub use crate::seasign::{SeaSign, SeaSignPrivateKey, SeaSignPublicKey, SeaSignSignature};
+pub use crate::params::{Csidh512, Csidh1024, Csidh1792, CsidhParams};
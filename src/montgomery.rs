@@ -1,34 +1,37 @@
-use crate::galois::{GaloisElement, LargeUint};
+use crate::error::CsidhError;
+use crate::field::{FieldUint, PrimeField};
+use zeroize::Zeroize;
 
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Curve {
-    pub a: GaloisElement,
-    b: GaloisElement,
+pub struct Curve<F: PrimeField> {
+    pub a: F,
+    pub b: F,
 }
 
-impl Curve {
-    pub fn new(a: LargeUint, b: LargeUint) -> Curve {
+impl<F: PrimeField> Curve<F> {
+    pub fn new(a: F::Uint, b: F::Uint) -> Curve<F> {
         Curve {
-            a: GaloisElement::from_large_uint(a),
-            b: GaloisElement::from_large_uint(b),
+            a: F::from_uint(a),
+            b: F::from_uint(b),
         }
     }
 
-    pub fn contains(&self, p: &Point) -> bool {
+    #[allow(dead_code)]
+    pub fn contains(&self, p: &Point<F>) -> bool {
         let left = (p.y * p.y) * self.b;
         let right = Curve::right_side(&self.a, &p.x);
 
         left == right
     }
 
-    fn recover(p: &Point, q: &ProjectivePoint, o: &ProjectivePoint) -> Point {
+    #[allow(dead_code)]
+    fn recover(p: &Point<F>, q: &ProjectivePoint<F>, o: &ProjectivePoint<F>) -> Point<F> {
         let v1 = p.x * q.z;
         let v2 = q.x + v1;
-        let v3 = q.x - v1;
-        let v3 = v3 * v3;
+        let v3 = (q.x - v1).square();
         let v3 = v3 * o.x;
-        let v1 = q.z * GaloisElement::from_u64(2) * p.curve.a;
+        let v1 = q.z * F::from_u64(2) * p.curve.a;
         let v2 = v2 + v1;
         let v4 = p.x * q.x;
         let v4 = v4 + q.z;
@@ -37,7 +40,7 @@ impl Curve {
         let v2 = v2 - v1;
         let v2 = v2 * o.z;
         let y  = v2 - v3;
-        let v1 = p.y * GaloisElement::from_u64(2) * p.curve.b;
+        let v1 = p.y * F::from_u64(2) * p.curve.b;
         let v1 = v1 * q.z;
         let v1 = v1 * o.z;
         let x  = v1 * q.x;
@@ -51,62 +54,141 @@ impl Curve {
         }
     }
 
-    pub fn right_side(a: &GaloisElement, x: &GaloisElement) -> GaloisElement {
+    pub fn right_side(a: &F, x: &F) -> F {
         let mut ret = *x;
         ret.square();
         let t = *a * *x;
         ret.add_from(&t);
-        ret.add_from(&crate::global::GAL_1);
+        ret.add_from(&F::one());
         ret.mul_with(x);
         return ret;
     }
 
-    pub fn isogeny(a: &mut ProjectivePoint, p: &mut ProjectivePoint, k: &ProjectivePoint, l: u64)
+    #[allow(dead_code)]
+    pub fn isogeny(a: &mut ProjectivePoint<F>, p: &mut ProjectivePoint<F>, k: &ProjectivePoint<F>,
+                   l: u64)
     {
-        let mut t = [k.z, k.x, k.x, k.z];
-        let mut tmp0;
-        let mut tmp1;
-        let mut q = ProjectivePoint::new(GaloisElement::from_u64(1), GaloisElement::from_u64(1));
-
-		q.x =  p.x * k.x;
-        tmp0 = p.z * k.z;
-        q.x.sub_from(&tmp0);
+        let mut points = [*p];
+        Curve::isogeny_points(a, &mut points, k, l);
+        *p = points[0];
+    }
 
-		q.z =  p.x * k.z;
-        tmp0 = p.z * k.x;
-        q.z.sub_from(&tmp0);
+    /// Like `isogeny`, but pushes several points through the same isogeny
+    pub fn isogeny_points<const N: usize>(a: &mut ProjectivePoint<F>,
+                                          points: &mut [ProjectivePoint<F>; N],
+                                          k: &ProjectivePoint<F>, l: u64)
+    {
+        let (mut t, mut q) = Curve::isogeny_start(points, k);
 
         let mut m = [*k; 3];
-        m[1] = k.double2(&a);
+        m[1] = k.double2(a);
 
         for i in 1..(l as usize / 2) {
             if i >= 2 {
                 m[i % 3] = m[(i -1) % 3].add(k, &m[(i - 2) % 3]);
             }
 
-            tmp0 = m[i % 3].x * t[0];
-            tmp1 = m[i % 3].z * t[1];
-            t[0] = tmp0 + tmp1;
+            Curve::isogeny_step(&mut t, &mut q, points, &m[i % 3]);
+        }
+
+        Curve::isogeny_finish(a, points, &mut t, &mut q);
+    }
+
+    /// Like `isogeny_points`, but takes as long as an isogeny of degree `l_max`
+    ///
+    /// The multiples of the kernel are computed up to the ones an isogeny of degree `l_max` needs,
+    /// those beyond `l` are masked out without branching. This is the Matryoshka isogeny of CTIDH,
+    /// it hides which degree up to `l_max` was used.
+    pub fn isogeny_points_matryoshka<const N: usize>(a: &mut ProjectivePoint<F>,
+                                                     points: &mut [ProjectivePoint<F>; N],
+                                                     k: &ProjectivePoint<F>, l: u64, l_max: u64)
+    {
+        let (mut t, mut q) = Curve::isogeny_start(points, k);
+
+        let mut m = [*k; 3];
+        m[1] = k.double2(a);
+
+        for i in 1..(l_max as usize / 2) {
+            if i >= 2 {
+                m[i % 3] = m[(i -1) % 3].add(k, &m[(i - 2) % 3]);
+            }
+
+            let mut new_t = t;
+            let mut new_q = q;
+            Curve::isogeny_step(&mut new_t, &mut new_q, points, &m[i % 3]);
+
+            let real = (i as u64) < l / 2;
+            for (t, new_t) in t.iter_mut().zip(new_t.iter()) {
+                t.conditional_assign(new_t, real);
+            }
+            for (q, new_q) in q.iter_mut().zip(new_q.iter()) {
+                q.conditional_assign(new_q, real);
+            }
+        }
+
+        Curve::isogeny_finish(a, points, &mut t, &mut q);
+    }
 
-            t[1].mul_with(&m[i % 3].x);
+    /// The products of the isogeny for the kernel point `k` itself
+    fn isogeny_start<const N: usize>(points: &[ProjectivePoint<F>; N], k: &ProjectivePoint<F>)
+        -> ([F; 4], [ProjectivePoint<F>; N])
+    {
+        let t = [k.z, k.x, k.x, k.z];
+        let mut tmp0;
+        let mut q = [ProjectivePoint::new(F::from_u64(1), F::from_u64(1)); N];
 
-            tmp0 = m[i % 3].z * t[2];
-            tmp1 = m[i % 3].x * t[3];
-            t[2] = tmp0 + tmp1;
+        for (q, p) in q.iter_mut().zip(points.iter()) {
+            q.x =  p.x * k.x;
+            tmp0 = p.z * k.z;
+            q.x.sub_from(&tmp0);
 
-            t[3].mul_with(&m[i % 3].z);
+            q.z =  p.x * k.z;
+            tmp0 = p.z * k.x;
+            q.z.sub_from(&tmp0);
+        }
 
+        (t, q)
+    }
 
-            tmp0 = p.x * m[i % 3].x;
-            tmp1 = p.z * m[i % 3].z;
+    /// Multiplies the next multiple `m` of the kernel into the products of the isogeny
+    fn isogeny_step<const N: usize>(t: &mut [F; 4], q: &mut [ProjectivePoint<F>; N],
+                                    points: &[ProjectivePoint<F>; N], m: &ProjectivePoint<F>)
+    {
+        let mut tmp0;
+        let mut tmp1;
+
+        tmp0 = m.x * t[0];
+        tmp1 = m.z * t[1];
+        t[0] = tmp0 + tmp1;
+
+        t[1].mul_with(&m.x);
+
+        tmp0 = m.z * t[2];
+        tmp1 = m.x * t[3];
+        t[2] = tmp0 + tmp1;
+
+        t[3].mul_with(&m.z);
+
+        for (q, p) in q.iter_mut().zip(points.iter()) {
+            tmp0 = p.x * m.x;
+            tmp1 = p.z * m.z;
             tmp0.sub_from(&tmp1);
             q.x.mul_with(&tmp0);
 
-            tmp0 = p.x * m[i % 3].z;
-            tmp1 = p.z * m[i % 3].x;
+            tmp0 = p.x * m.z;
+            tmp1 = p.z * m.x;
             tmp0.sub_from(&tmp1);
             q.z.mul_with(&tmp0);
         }
+    }
+
+    /// Computes the codomain and the images of the points from the products of the isogeny
+    fn isogeny_finish<const N: usize>(a: &mut ProjectivePoint<F>,
+                                      points: &mut [ProjectivePoint<F>; N], t: &mut [F; 4],
+                                      q: &mut [ProjectivePoint<F>; N])
+    {
+        let mut tmp0;
+        let mut tmp1;
 
         t[0].mul_with(&{t[1]});
         t[0].add_from(&{t[0]});
@@ -133,87 +215,112 @@ impl Curve {
         t[3].square();
         a.z = a.z * t[3];
 
-        q.x.square();
-        q.z.square();
-		p.x.mul_with(&q.x);
-		p.z.mul_with(&q.z);
+        for (q, p) in q.iter_mut().zip(points.iter_mut()) {
+            q.x.square();
+            q.z.square();
+            p.x.mul_with(&q.x);
+            p.z.mul_with(&q.z);
+        }
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq)]
-pub struct Point {
-    curve: Curve,
-    x: GaloisElement,
-    y: GaloisElement,
-    z: GaloisElement,
+pub struct Point<F: PrimeField> {
+    pub curve: Curve<F>,
+    pub x: F,
+    pub y: F,
+    pub z: F,
 }
 
-impl Point {
-    pub fn new(curve: Curve, x: LargeUint, y: LargeUint) -> Point {
+#[allow(dead_code)]
+impl<F: PrimeField> Point<F> {
+    pub fn new(curve: Curve<F>, x: F::Uint, y: F::Uint) -> Point<F> {
         Point {
-            x: GaloisElement::from_large_uint(x),
-            y: GaloisElement::from_large_uint(y),
-            z: GaloisElement::from_u64(1),
+            x: F::from_uint(x),
+            y: F::from_uint(y),
+            z: F::from_u64(1),
             curve,
         }
     }
 
-    pub fn multiply(&self, k: &LargeUint) -> Point {
-        let (x0, x1) = self.projectivize().ladder(&self.curve.a, &k);
+    pub fn multiply(&self, k: &F::Uint) -> Result<Point<F>, CsidhError> {
+        let (x0, x1) = self.projectivize().ladder(&self.curve.a, k);
         let q = Curve::recover(self, &x0, &x1);
         return q.unproject();
     }
 
-    fn projectivize(&self) -> ProjectivePoint {
+    fn projectivize(&self) -> ProjectivePoint<F> {
 
-        let zero = GaloisElement::from_u64(0);
+        let zero = F::from_u64(0);
 
         if self.x == zero || self.z == zero {
             ProjectivePoint {
-                x: GaloisElement::from_u64(1),
-                z: GaloisElement::from_u64(0),
+                x: F::from_u64(1),
+                z: F::from_u64(0),
             }
         } else {
             ProjectivePoint {
-                x: self.x.clone(),
-                z: GaloisElement::from_u64(1),
+                x: self.x,
+                z: F::from_u64(1),
             }
         }
     }
 
-    fn unproject(self) -> Point {
-        assert!(self.z != GaloisElement::from_u64(0));
+    fn unproject(self) -> Result<Point<F>, CsidhError> {
+        if self.z == F::from_u64(0) {
+            return Err(CsidhError::PointAtInfinity);
+        }
 
         let x = self.x / self.z;
         let y = self.y / self.z;
-        let z = self.z / self.z;
+        let z = F::from_u64(1);
 
-        Point {
+        Ok(Point {
             x, y, z,
             curve: self.curve,
-        }
+        })
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct ProjectivePoint {
-    pub x: GaloisElement,
-    pub z: GaloisElement,
+pub struct ProjectivePoint<F: PrimeField> {
+    pub x: F,
+    pub z: F,
 }
 
-impl ProjectivePoint {
-    pub fn new(x: GaloisElement, z: GaloisElement) -> ProjectivePoint {
+impl<F: PrimeField> Zeroize for ProjectivePoint<F> {
+    fn zeroize(&mut self) {
+        self.x.zeroize();
+        self.z.zeroize();
+    }
+}
+
+impl<F: PrimeField> ProjectivePoint<F> {
+    pub fn new(x: F, z: F) -> ProjectivePoint<F> {
         ProjectivePoint {
             x, z
         }
     }
 
     pub fn is_infinity(&self) -> bool {
-        self.z == GaloisElement::from_u64(0)
+        self.z == F::from_u64(0)
     }
 
-    fn double_add(r: &mut ProjectivePoint, s: &mut ProjectivePoint, p: &ProjectivePoint,
-                  q: &ProjectivePoint, pq: &ProjectivePoint, curve: &ProjectivePoint) {
+    /// Sets `self` to `other` if `choice` is true, without branching on `choice`
+    pub fn conditional_assign(&mut self, other: &ProjectivePoint<F>, choice: bool) {
+        self.x.conditional_assign(&other.x, choice);
+        self.z.conditional_assign(&other.z, choice);
+    }
+
+    /// Swaps `a` and `b` if `choice` is true, without branching on `choice`
+    pub fn conditional_swap(a: &mut ProjectivePoint<F>, b: &mut ProjectivePoint<F>, choice: bool) {
+        F::conditional_swap(&mut a.x, &mut b.x, choice);
+        F::conditional_swap(&mut a.z, &mut b.z, choice);
+    }
+
+    fn double_add(r: &mut ProjectivePoint<F>, s: &mut ProjectivePoint<F>, p: &ProjectivePoint<F>,
+                  q: &ProjectivePoint<F>, pq: &ProjectivePoint<F>, curve: &ProjectivePoint<F>) {
         let mut a = q.x + q.z;
         let mut b = q.x - q.z;
         let mut c = p.x + p.z;
@@ -239,10 +346,10 @@ impl ProjectivePoint {
         s.z = pq.x * d;
     }
 
-    pub fn ladder2(&self, curve: &ProjectivePoint, k: &LargeUint) -> ProjectivePoint {
+    pub fn ladder2(&self, curve: &ProjectivePoint<F>, k: &F::Uint) -> ProjectivePoint<F> {
         let copy = *self;
         let mut r = *self;
-        let mut ret = ProjectivePoint::new(GaloisElement::from_u64(1), GaloisElement::from_u64(0));
+        let mut ret = ProjectivePoint::new(F::from_u64(1), F::from_u64(0));
 
         let mut rr = &mut r;
         let mut rret = &mut ret;
@@ -253,22 +360,50 @@ impl ProjectivePoint {
             let bit = k.bit(i);
 
             if bit {
-                std::mem::swap(&mut rret, &mut rr);
+                core::mem::swap(&mut rret, &mut rr);
             }
 
             let r2 = *rr;
             let ret2 = *rret;
-            ProjectivePoint::double_add(&mut rret, &mut rr, &ret2, &r2, &copy, curve);
+            ProjectivePoint::double_add(rret, rr, &ret2, &r2, &copy, curve);
 
             if bit {
-                std::mem::swap(&mut rret, &mut rr);
+                core::mem::swap(&mut rret, &mut rr);
             }
         }
 
         return ret;
     }
 
-    pub fn double2(&self, curve: &ProjectivePoint) -> ProjectivePoint {
+    /// Like `ladder2`, but the sequence of operations does not depend on `k`
+    ///
+    /// The ladder always runs over the lowest `bits` bits of `k`, which therefore has to be a
+    /// public upper bound on the length of the scalar. The points are exchanged with masked
+    /// swaps instead of branches.
+    pub fn ladder2_constant_time(&self, curve: &ProjectivePoint<F>, k: &F::Uint, bits: u64)
+        -> ProjectivePoint<F>
+    {
+        let mut r = *self;
+        let mut ret = ProjectivePoint::new(F::from_u64(1), F::from_u64(0));
+        let mut swapped = false;
+
+        for i in (0..bits).rev() {
+            let bit = k.bit(i);
+
+            ProjectivePoint::conditional_swap(&mut ret, &mut r, bit ^ swapped);
+            swapped = bit;
+
+            let r2 = r;
+            let ret2 = ret;
+            ProjectivePoint::double_add(&mut ret, &mut r, &ret2, &r2, self, curve);
+        }
+
+        ProjectivePoint::conditional_swap(&mut ret, &mut r, swapped);
+
+        return ret;
+    }
+
+    pub fn double2(&self, curve: &ProjectivePoint<F>) -> ProjectivePoint<F> {
         let mut a = self.x + self.z;
         a.square();
         let mut b = self.x - self.z;
@@ -286,11 +421,19 @@ impl ProjectivePoint {
         return ProjectivePoint { x: qx, z: qz };
     }
 
-    pub fn ladder(&self, a: &GaloisElement, k: &LargeUint) -> (ProjectivePoint, ProjectivePoint) {
-        let mut x0 = self.clone();
+    #[allow(dead_code)]
+    pub fn ladder(&self, a: &F, k: &F::Uint)
+        -> (ProjectivePoint<F>, ProjectivePoint<F>)
+    {
+        let mut x0 = *self;
         let mut x1 = self.double(a);
 
-        if k == &LargeUint::from_u64(1) {
+        if k == &F::Uint::from_u64(0) {
+            let infinity = ProjectivePoint::new(F::from_u64(1), F::from_u64(0));
+            return (infinity, x0);
+        }
+
+        if k == &F::Uint::from_u64(1) {
             return (x0, x1);
         }
 
@@ -311,17 +454,15 @@ impl ProjectivePoint {
     }
 
 
-    pub fn add(&self, other: &ProjectivePoint, orig: &ProjectivePoint) -> ProjectivePoint {
+    pub fn add(&self, other: &ProjectivePoint<F>, orig: &ProjectivePoint<F>) -> ProjectivePoint<F> {
         let v0 = self.x + self.z;
         let v1 = other.x - other.z;
         let v1 = v1 * v0;
         let v0 = self.x - self.z;
         let v2 = other.x + other.z;
         let v2 = v2 * v0;
-        let v3 = v1 + v2;
-        let v3 = v3 * v3;
-        let v4 = v1 - v2;
-        let v4 = v4 * v4;
+        let v3 = (v1 + v2).square();
+        let v4 = (v1 - v2).square();
         let x = orig.z * v3;
         let z = orig.x * v4;
 
@@ -331,7 +472,8 @@ impl ProjectivePoint {
         }
     }
 
-    pub fn double(&self, a: &GaloisElement) -> ProjectivePoint {
+    #[allow(dead_code)]
+    pub fn double(&self, a: &F) -> ProjectivePoint<F> {
         let v1 = self.x + self.z;
         let v1 = v1 * v1;
         let v2 = self.x - self.z;
@@ -339,8 +481,8 @@ impl ProjectivePoint {
         let x = v1 * v2;
         let v1 = v1 - v2;
         // Here was a bug! I forgot that division -> Modulo Ring
-        let a_2 = *a + GaloisElement::from_u64(2);
-        let v3 = v1 * (a_2 / GaloisElement::from_u64(4));
+        let a_2 = *a + F::from_u64(2);
+        let v3 = v1 * (a_2 / F::from_u64(4));
         let v3 = v3 + v2;
         let z = v1 * v3;
 
@@ -350,10 +492,17 @@ impl ProjectivePoint {
         }
     }
 
-    fn normalize(&mut self) {
+    pub fn normalize(&mut self) {
         self.z.inverse();
         self.x.mul_with(&self.z);
-        self.z = GaloisElement::from_u64(1);
+        self.z = F::from_u64(1);
+    }
+
+    /// Like `normalize`, but in variable time, for public points only
+    pub fn normalize_vartime(&mut self) {
+        self.z.inverse_vartime();
+        self.x.mul_with(&self.z);
+        self.z = F::from_u64(1);
     }
 }
 
@@ -361,15 +510,152 @@ impl ProjectivePoint {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::global;
+    use crate::params::{Csidh512, CsidhParams};
+
+    use rand::{CryptoRng, Rng};
+    use core::ops::{Add, Sub, Mul, Div};
+
+    type GaloisElement = crate::galois::GaloisElement<Csidh512>;
+    type LargeUint = crate::galois::LargeUint<Csidh512>;
+    type Curve = super::Curve<GaloisElement>;
+    type Point = super::Point<GaloisElement>;
+    type ProjectivePoint = super::ProjectivePoint<GaloisElement>;
+
+    /// The toy CSIDH prime `4 * 3 * 5 * 7 - 1`, small enough to check the curves exhaustively
+    const TOY_P: u64 = 419;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Toy(u64);
+
+    impl Toy {
+        fn pow(mut self, mut e: u64) -> Toy {
+            let mut r = Toy(1);
+            while e != 0 {
+                if e & 1 == 1 {
+                    r = r * self;
+                }
+                self = self * self;
+                e >>= 1;
+            }
+            r
+        }
+    }
+
+    impl Zeroize for Toy {
+        fn zeroize(&mut self) {
+            self.0.zeroize();
+        }
+    }
+
+    impl Add for Toy {
+        type Output = Toy;
+
+        fn add(self, other: Toy) -> Toy {
+            Toy((self.0 + other.0) % TOY_P)
+        }
+    }
+
+    impl Sub for Toy {
+        type Output = Toy;
+
+        fn sub(self, other: Toy) -> Toy {
+            Toy((self.0 + TOY_P - other.0) % TOY_P)
+        }
+    }
+
+    impl Mul for Toy {
+        type Output = Toy;
+
+        fn mul(self, other: Toy) -> Toy {
+            Toy(self.0 * other.0 % TOY_P)
+        }
+    }
+
+    impl Div for Toy {
+        type Output = Toy;
+
+        fn div(self, other: Toy) -> Toy {
+            self * other.pow(TOY_P - 2)
+        }
+    }
+
+    impl PrimeField for Toy {
+        type Uint = u64;
+
+        fn one() -> Toy {
+            Toy(1)
+        }
+
+        fn from_u64(u: u64) -> Toy {
+            Toy(u % TOY_P)
+        }
+
+        fn from_uint(u: u64) -> Toy {
+            Toy(u)
+        }
+
+        fn into_uint(&self) -> u64 {
+            self.0
+        }
+
+        fn random_element<R: Rng + CryptoRng>(rng: &mut R) -> Toy {
+            Toy(rng.gen_range(0, TOY_P))
+        }
+
+        fn add_from(&mut self, other: &Toy) {
+            *self = *self + *other;
+        }
+
+        fn sub_from(&mut self, other: &Toy) {
+            *self = *self - *other;
+        }
+
+        fn mul_with(&mut self, other: &Toy) {
+            *self = *self * *other;
+        }
+
+        fn square(&mut self) -> Toy {
+            *self = *self * *self;
+            *self
+        }
+
+        fn inverse(&mut self) {
+            *self = self.pow(TOY_P - 2);
+        }
+
+        fn is_square(&self) -> bool {
+            self.pow((TOY_P - 1) / 2) == Toy(1)
+        }
+
+        fn conditional_assign(&mut self, other: &Toy, choice: bool) {
+            if choice {
+                *self = *other;
+            }
+        }
+
+        fn conditional_swap(a: &mut Toy, b: &mut Toy, choice: bool) {
+            if choice {
+                core::mem::swap(a, b);
+            }
+        }
+    }
+
+    /// Whether every point on the curve with coefficient `a` and on its twist has an order
+    /// dividing `p + 1`, which is the case exactly for supersingular curves
+    fn toy_supersingular(a: Toy) -> bool {
+        let curve = super::ProjectivePoint::new(a, Toy(1));
+        (0..TOY_P)
+            .map(|x| super::ProjectivePoint::new(Toy(x), Toy(1)))
+            .all(|p| p.ladder2(&curve, &(TOY_P + 1)).is_infinity())
+    }
 
     #[test]
     fn check_mul() {
-        let x = LargeUint::parse_bytes(b"2051044887188588280366899510711463515184102432059522841387541984999186019238289110841661333718393379209806643406155944602233875537370058705956384966209858");
-        let y = LargeUint::parse_bytes(b"2999054700883294606115636709285947688603015463995111523694534197644452886751843273757676343103953201273958036952062931228773567734286840492294219977378136");
+        let x = LargeUint::parse_bytes(b"2051044887188588280366899510711463515184102432059522841387541984999186019238289110841661333718393379209806643406155944602233875537370058705956384966209858").unwrap();
+        let y = LargeUint::parse_bytes(b"2999054700883294606115636709285947688603015463995111523694534197644452886751843273757676343103953201273958036952062931228773567734286840492294219977378136").unwrap();
 
-        let other_x = LargeUint::parse_bytes(b"1254817631949275079030490581963578364746575569014839158947538007979236709253796922466332191140273712204313677321924940880514829958528954596325165920058277");
-        let other_y = LargeUint::parse_bytes(b"2381495309685763751265865484184529659090354786855457591442552214156841700513768692570497752099605704710183797526595611214891101033449784504091079214700929");
+        let other_x = LargeUint::parse_bytes(b"1254817631949275079030490581963578364746575569014839158947538007979236709253796922466332191140273712204313677321924940880514829958528954596325165920058277").unwrap();
+        let other_y = LargeUint::parse_bytes(b"2381495309685763751265865484184529659090354786855457591442552214156841700513768692570497752099605704710183797526595611214891101033449784504091079214700929").unwrap();
 
         let a = 0u32.into();
         let b = 1u32.into();
@@ -379,7 +665,7 @@ mod test {
         let other_point = Point::new(curve, other_x, other_y);
         assert!(curve.contains(&other_point));
 
-        let multiplied = point.multiply(&LargeUint::from_u64(9u64)).unproject();
+        let multiplied = point.multiply(&LargeUint::from_u64(9u64)).unwrap().unproject().unwrap();
 
         println!("X: {}\nY: {}\nZ: {}\n\nX: {}\nY: {}\nZ: {}",
                  multiplied.x.into_large_uint(), multiplied.y.into_large_uint(), multiplied.z.into_large_uint(),
@@ -388,10 +674,21 @@ mod test {
         assert_eq!(other_point, multiplied);
     }
 
+    #[test]
+    fn check_mul_zero() {
+        let x = LargeUint::parse_bytes(b"2051044887188588280366899510711463515184102432059522841387541984999186019238289110841661333718393379209806643406155944602233875537370058705956384966209858").unwrap();
+        let y = LargeUint::parse_bytes(b"2999054700883294606115636709285947688603015463995111523694534197644452886751843273757676343103953201273958036952062931228773567734286840492294219977378136").unwrap();
+
+        let curve = Curve::new(0u32.into(), 1u32.into());
+        let point = Point::new(curve, x, y);
+
+        assert_eq!(point.multiply(&LargeUint::from_u64(0)), Err(CsidhError::PointAtInfinity));
+    }
+
     #[test]
     fn check_ladder2() {
-        let x = LargeUint::parse_bytes(b"2051044887188588280366899510711463515184102432059522841387541984999186019238289110841661333718393379209806643406155944602233875537370058705956384966209858");
-        let y = LargeUint::parse_bytes(b"2999054700883294606115636709285947688603015463995111523694534197644452886751843273757676343103953201273958036952062931228773567734286840492294219977378136");
+        let x = LargeUint::parse_bytes(b"2051044887188588280366899510711463515184102432059522841387541984999186019238289110841661333718393379209806643406155944602233875537370058705956384966209858").unwrap();
+        let y = LargeUint::parse_bytes(b"2999054700883294606115636709285947688603015463995111523694534197644452886751843273757676343103953201273958036952062931228773567734286840492294219977378136").unwrap();
 
         let a = 0u32.into();
         let b = 1u32.into();
@@ -411,29 +708,123 @@ mod test {
     }
 
     #[test]
-    fn check_isogeny() {
-        let x = LargeUint::parse_bytes(b"2051044887188588280366899510711463515184102432059522841387541984999186019238289110841661333718393379209806643406155944602233875537370058705956384966209858");
-        let y = LargeUint::parse_bytes(b"2999054700883294606115636709285947688603015463995111523694534197644452886751843273757676343103953201273958036952062931228773567734286840492294219977378136");
+    fn check_ladder2_constant_time() {
+        let x = LargeUint::parse_bytes(b"2051044887188588280366899510711463515184102432059522841387541984999186019238289110841661333718393379209806643406155944602233875537370058705956384966209858").unwrap();
 
-        let other_x = LargeUint::parse_bytes(b"1254817631949275079030490581963578364746575569014839158947538007979236709253796922466332191140273712204313677321924940880514829958528954596325165920058277");
-        let other_y = LargeUint::parse_bytes(b"2381495309685763751265865484184529659090354786855457591442552214156841700513768692570497752099605704710183797526595611214891101033449784504091079214700929");
+        let curve = Curve::new(0u32.into(), 1u32.into());
+        let p_curve = ProjectivePoint::new(curve.a, GaloisElement::from_u64(1));
+        let point = ProjectivePoint::new(GaloisElement::from_large_uint(x), GaloisElement::from_u64(1));
+
+        let scalars = [
+            LargeUint::from_u64(0),
+            LargeUint::from_u64(1),
+            LargeUint::from_u64(5),
+            LargeUint::from_u64(587),
+            LargeUint { elements: Csidh512::P_MINUS_1_HALVES },
+        ];
+
+        for k in scalars.iter() {
+            let mut expected = point.ladder2(&p_curve, k);
+            let mut exact = point.ladder2_constant_time(&p_curve, k, k.bits());
+            let mut padded = point.ladder2_constant_time(&p_curve, k, Csidh512::PBITS);
+
+            assert_eq!(expected.is_infinity(), exact.is_infinity());
+            assert_eq!(expected.is_infinity(), padded.is_infinity());
+            if !expected.is_infinity() {
+                expected.normalize();
+                exact.normalize();
+                padded.normalize();
+                assert_eq!(expected, exact);
+                assert_eq!(expected, padded);
+            }
+        }
+    }
+
+    #[test]
+    fn check_isogeny() {
+        let other_x = LargeUint::parse_bytes(b"1254817631949275079030490581963578364746575569014839158947538007979236709253796922466332191140273712204313677321924940880514829958528954596325165920058277").unwrap();
+        let other_y = LargeUint::parse_bytes(b"2381495309685763751265865484184529659090354786855457591442552214156841700513768692570497752099605704710183797526595611214891101033449784504091079214700929").unwrap();
 
         let a = 0u32.into();
         let b = 1u32.into();
         let curve = Curve::new(a, b);
-        let point = Point::new(curve, x, y);
-        assert!(curve.contains(&point));
         let other_point = Point::new(curve, other_x, other_y);
+        assert!(curve.contains(&other_point));
 
         let mut proj_c = ProjectivePoint::new(curve.a, GaloisElement::from_u64(3));
-        let proj_other = other_point.projectivize().ladder2(&proj_c, &LargeUint::from_u64(3));;
-        let mut proj_point = point.projectivize();
 
-        Curve::isogeny(&mut proj_c, &mut proj_point, &proj_other, 3);
+        // (p + 1) / 3 kills everything but the 3-torsion
+        let mut cof = LargeUint::from_u64(4);
+        for &l in Csidh512::PRIMES.iter().skip(1) {
+            cof.mul_with_u64(l);
+        }
+
+        let kernel = (1..)
+            .map(GaloisElement::from_u64)
+            .filter(|x| Curve::right_side(&curve.a, x).is_square())
+            .map(|x| ProjectivePoint::new(x, GaloisElement::from_u64(1)).ladder2(&proj_c, &cof))
+            .find(|k| !k.is_infinity())
+            .unwrap();
+        let mut proj_point = other_point.projectivize();
+
+        Curve::isogeny(&mut proj_c, &mut proj_point, &kernel, 3);
 
         proj_c.z.inverse();
         proj_c.x.mul_with(&proj_c.z);
+        proj_point.normalize();
 
         assert!(Curve::right_side(&proj_c.x, &proj_point.x).is_square());
     }
+
+    #[test]
+    fn check_toy_isogeny() {
+        assert!(toy_supersingular(Toy(0)));
+        assert!(!toy_supersingular(Toy(1)));
+
+        let curve = super::ProjectivePoint::new(Toy(0), Toy(1));
+        for &l in [3, 5, 7].iter() {
+            let kernel = (1..TOY_P)
+                .map(|x| super::ProjectivePoint::new(Toy(x), Toy(1)))
+                .filter(|p| super::Curve::right_side(&Toy(0), &p.x).is_square())
+                .map(|p| p.ladder2(&curve, &((TOY_P + 1) / l)))
+                .find(|k| !k.is_infinity())
+                .unwrap();
+
+            let mut image = curve;
+            let mut point = kernel;
+            super::Curve::isogeny(&mut image, &mut point, &kernel, l);
+            image.normalize();
+
+            assert!(point.is_infinity());
+            assert_ne!(image.x, Toy(0));
+            assert!(toy_supersingular(image.x));
+        }
+    }
+
+    #[test]
+    fn check_toy_matryoshka() {
+        let curve = super::ProjectivePoint::new(Toy(0), Toy(1));
+        for &l in [3, 5, 7].iter() {
+            let kernel = (1..TOY_P)
+                .map(|x| super::ProjectivePoint::new(Toy(x), Toy(1)))
+                .map(|p| p.ladder2(&curve, &((TOY_P + 1) / l)))
+                .find(|k| !k.is_infinity())
+                .unwrap();
+            let points = [super::ProjectivePoint::new(Toy(2), Toy(1)), kernel];
+
+            let mut expected = (curve, points);
+            super::Curve::isogeny_points(&mut expected.0, &mut expected.1, &kernel, l);
+            let mut matryoshka = (curve, points);
+            super::Curve::isogeny_points_matryoshka(&mut matryoshka.0, &mut matryoshka.1, &kernel,
+                                                    l, 7);
+
+            expected.0.normalize();
+            matryoshka.0.normalize();
+            assert_eq!(expected.0, matryoshka.0, "l = {}", l);
+            expected.1[0].normalize();
+            matryoshka.1[0].normalize();
+            assert_eq!(expected.1[0], matryoshka.1[0], "l = {}", l);
+            assert!(matryoshka.1[1].is_infinity());
+        }
+    }
 }
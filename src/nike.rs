@@ -0,0 +1,104 @@
+//! A common interface for non-interactive key exchanges
+
+use rand::prelude::*;
+
+use crate::csidh::{CsidhSharedSecret, PrivateKey, PublicKey};
+use crate::error::CsidhError;
+use crate::keyspace::KeySpace;
+use crate::params::CsidhParams;
+
+/// A non-interactive key exchange
+///
+/// Both parties publish a public key once, afterwards each of them can compute the shared secret
+/// from their own private key and the public key of the other party alone. This lets protocol
+/// code be written once for any key exchange.
+///
+/// Every CSIDH parameter set implements this with keys drawn from its default `KeySpace`:
+///
+/// ```rust,no_run
+/// # use csidh::{Csidh512, Nike};
+/// fn exchange<N: Nike>() -> Result<(), N::Error>
+///     where N::SharedSecret: PartialEq + std::fmt::Debug
+/// {
+///     let mut rng = rand::thread_rng();
+///     let (a, b) = (N::keygen(&mut rng)?, N::keygen(&mut rng)?);
+///     let (a_public, b_public) = (N::public_key(&a)?, N::public_key(&b)?);
+///
+///     assert_eq!(N::shared_secret(&a, &b_public)?, N::shared_secret(&b, &a_public)?);
+///     Ok(())
+/// }
+///
+/// exchange::<Csidh512>().unwrap();
+/// ```
+pub trait Nike {
+    /// The secret key of one party
+    type PrivateKey;
+    /// The key a party publishes
+    type PublicKey;
+    /// The secret both parties arrive at
+    type SharedSecret;
+    /// The error returned when an operation fails
+    type Error;
+
+    /// Generates a new private key with randomness from `rng`
+    fn keygen<R: CryptoRng + Rng>(rng: &mut R) -> Result<Self::PrivateKey, Self::Error>;
+
+    /// Computes the public key belonging to `private`
+    fn public_key(private: &Self::PrivateKey) -> Result<Self::PublicKey, Self::Error>;
+
+    /// Computes the secret shared between the owner of `private` and the owner of `public`
+    fn shared_secret(private: &Self::PrivateKey, public: &Self::PublicKey)
+        -> Result<Self::SharedSecret, Self::Error>;
+}
+
+impl<P: CsidhParams> Nike for P {
+    type PrivateKey = PrivateKey<P>;
+    type PublicKey = PublicKey<P>;
+    type SharedSecret = CsidhSharedSecret<P>;
+    type Error = CsidhError;
+
+    fn keygen<R: CryptoRng + Rng>(rng: &mut R) -> Result<PrivateKey<P>, CsidhError> {
+        Ok(PrivateKey::generate_new(&KeySpace::default(), rng))
+    }
+
+    fn public_key(private: &PrivateKey<P>) -> Result<PublicKey<P>, CsidhError> {
+        Ok(private.get_public_key())
+    }
+
+    fn shared_secret(private: &PrivateKey<P>, public: &PublicKey<P>)
+        -> Result<CsidhSharedSecret<P>, CsidhError>
+    {
+        private.get_shared_secret(public)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::Toy;
+
+    fn exchange<N: Nike>() -> (N::SharedSecret, N::SharedSecret) {
+        let mut rng = thread_rng();
+        let a = N::keygen(&mut rng).ok().unwrap();
+        let b = N::keygen(&mut rng).ok().unwrap();
+        let a_public = N::public_key(&a).ok().unwrap();
+        let b_public = N::public_key(&b).ok().unwrap();
+
+        let a_shared = N::shared_secret(&a, &b_public).ok().unwrap();
+        let b_shared = N::shared_secret(&b, &a_public).ok().unwrap();
+        (a_shared, b_shared)
+    }
+
+    #[test]
+    fn check_exchange() {
+        let (a, b) = exchange::<Toy>();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn check_invalid_public_key() {
+        let private = Toy::keygen(&mut thread_rng()).unwrap();
+        let invalid = PublicKey::<Toy>::from_bytes(&[2, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(Toy::shared_secret(&private, &invalid), Err(CsidhError::InvalidPublicKey));
+    }
+}
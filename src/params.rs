@@ -0,0 +1,494 @@
+//! The parameter sets CSIDH can be instantiated with
+//!
+//! Every set is a marker type implementing `CsidhParams`, which carries the prime and all the
+//! constants derived from it. Fields, curves and keys take the set as a type parameter, e.g.
+//! `PrivateKey<Csidh1024>`.
+
+use core::fmt::Debug;
+use core::ops::{Index, IndexMut};
+use zeroize::Zeroize;
+
+/// A fixed-size array, used for the storage of the types generic over a parameter set
+pub trait Array<T>: Copy + Debug + Eq + Index<usize, Output = T> + IndexMut<usize>
+    + AsRef<[T]> + AsMut<[T]> + Zeroize + Send + Sync + 'static
+{
+    /// The number of elements
+    const LEN: usize;
+
+    /// Returns an array with every element set to `value`
+    fn filled(value: T) -> Self;
+}
+
+impl<T, const N: usize> Array<T> for [T; N]
+    where T: Copy + Debug + Eq + Zeroize + Send + Sync + 'static
+{
+    const LEN: usize = N;
+
+    fn filled(value: T) -> Self {
+        [value; N]
+    }
+}
+
+/// A CSIDH parameter set
+///
+/// The prime has the form `p = 4 * l_1 * ... * l_n - 1` for small odd primes `l_i`. All
+/// multi-precision constants are little-endian 64 bit limbs.
+pub trait CsidhParams: Copy + Debug + PartialEq + Eq + Send + Sync + 'static {
+    /// Enough limbs to hold `p`
+    type Limbs: Array<u64>;
+    /// One limb more than `Limbs`, the scratch space of the Montgomery multiplication
+    type WideLimbs: Array<u64>;
+    /// Enough signed 62 bit limbs to hold `2 * p`, the numbers of the safegcd inversion
+    type SignedLimbs: Array<i64>;
+    /// One exponent per prime `l_i`
+    type Exponents: Array<i8>;
+    /// An encoded field element
+    type Bytes: Array<u8>;
+    /// An encoded private key, one byte per exponent
+    type KeyBytes: Array<u8>;
+    /// A private key in the packed format of the reference implementation, half a byte per
+    /// exponent
+    type PackedKeyBytes: Array<u8>;
+
+    /// The number of limbs of a field element
+    const LIMBS: usize = <Self::Limbs as Array<u64>>::LEN;
+    /// The number of small primes `l_i`
+    const NUM_PRIMES: usize = <Self::Exponents as Array<i8>>::LEN;
+
+    /// The bit length of `p`
+    const PBITS: u64;
+    /// The largest absolute value an exponent of a private key may have
+    const MAX_EXPONENT: i8;
+
+    /// The small primes `l_i` in ascending order
+    const PRIMES: &'static [u64];
+
+    /// The strategy of the group action, a binary tree over `PRIMES` with its nodes in preorder
+    ///
+    /// The entry of a node splitting its primes into the first `k` and the rest is `k` if the
+    /// point is multiplied by the first part and the rest is handled first, and `-k` if the
+    /// roles are the other way around. The tree minimizes the cost of the scalar multiplications
+    /// plus that of pushing the points saved for later through the isogenies.
+    const STRATEGY: &'static [i16];
+
+    /// `-p^-1 mod 2^64`
+    const INV_MIN_P_MOD_R: u64;
+
+    const P: Self::Limbs;
+    const P_MINUS_2: Self::Limbs;
+    const P_MINUS_1_HALVES: Self::Limbs;
+    /// `R^2 mod p` for the Montgomery radix `R = 2^(64 * LIMBS)`
+    const R_SQUARED_MOD_P: Self::Limbs;
+    /// `R mod p`, the Montgomery form of one
+    const R_MOD_P: Self::Limbs;
+    /// `floor(4 * sqrt(p))`, a point of larger order proves supersingularity
+    const FOUR_SQRT_P: Self::Limbs;
+}
+
+/// The original CSIDH-512 parameters from the CSIDH paper
+///
+/// `p = 4 * 3 * 5 * ... * 373 * 587 - 1` is a 511 bit prime, exponents are sampled from
+/// `-5..=5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Csidh512;
+
+impl CsidhParams for Csidh512 {
+    type Limbs = [u64; 8];
+    type WideLimbs = [u64; 9];
+    type SignedLimbs = [i64; 9];
+    type Exponents = [i8; 74];
+    type Bytes = [u8; 64];
+    type KeyBytes = [u8; 74];
+    type PackedKeyBytes = [u8; 37];
+
+    const PBITS: u64 = 511;
+    const MAX_EXPONENT: i8 = 5;
+
+    const PRIMES: &'static [u64] = &[
+        3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+        101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191,
+        193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283,
+        293, 307, 311, 313, 317, 331, 337, 347, 349, 353, 359, 367, 373, 587
+    ];
+
+    const STRATEGY: &'static [i16] = &[
+        -37, -22, -14, -10, -7, -5, -4, -3, -2, 1, -1, -2, -1, -2, -1, -1, -4, -2, -1, 1, -2, -1,
+        -1, -6, -2, -1, -1, -1, -1, -3, 2, -1, -2, -1, -1, -1, -1, -10, -3, -1, -1, -2, -1, -1, -1,
+        -1, -1, -7, -2, -1, 4, -1, -1, -1, -5, -1, -1, -1, -1, -4, -1, -1, -1, -3, -1, -1, -1, -1,
+        -1, -1, -1, -1, -1
+    ];
+
+    const INV_MIN_P_MOD_R: u64 = 0x66c1301f632e294d;
+
+    const P: [u64; 8] = [
+        0x1b81b90533c6c87b, 0xc2721bf457aca835, 0x516730cc1f0b4f25, 0xa7aac6c567f35507,
+        0x5afbfcc69322c9cd, 0xb42d083aedc88c42, 0xfc8ab0d15e3e4c4a, 0x65b48e8f740f89bf,
+    ];
+
+    const P_MINUS_2: [u64; 8] = [
+        0x1b81b90533c6c879, 0xc2721bf457aca835, 0x516730cc1f0b4f25, 0xa7aac6c567f35507,
+        0x5afbfcc69322c9cd, 0xb42d083aedc88c42, 0xfc8ab0d15e3e4c4a, 0x65b48e8f740f89bf,
+    ];
+
+    const P_MINUS_1_HALVES: [u64; 8] = [
+        0x8dc0dc8299e3643d, 0xe1390dfa2bd6541a, 0xa8b398660f85a792, 0xd3d56362b3f9aa83,
+        0x2d7dfe63499164e6, 0x5a16841d76e44621, 0xfe455868af1f2625, 0x32da4747ba07c4df,
+    ];
+
+    const R_SQUARED_MOD_P: [u64; 8] = [
+        0x36905b572ffc1724, 0x67086f4525f1f27d, 0x4faf3fbfd22370ca, 0x192ea214bcc584b1,
+        0x5dae03ee2f5de3d0, 0x1e9248731776b371, 0xad5f166e20e4f52d, 0x4ed759aea6f3917e,
+    ];
+
+    const R_MOD_P: [u64; 8] = [
+        0xc8fc8df598726f0a, 0x7b1bc81750a6af95, 0x5d319e67c1e961b4, 0xb0aa7275301955f1,
+        0x4a080672d9ba6c64, 0x97a5ef8a246ee77b, 0x06ea9e5d4383676a, 0x3496e2e117e0ec80,
+    ];
+
+    const FOUR_SQRT_P: [u64; 8] = [
+        0x17895e71e1a20b3f, 0x38d0cd95f8636a56, 0x142b9541e59682cd, 0x856f1399d91d6592,
+        0x0000000000000002, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    ];
+}
+
+/// CSIDH-1024, one of the larger parameter sets proposed after the quantum cost analyses of CSIDH
+///
+/// `p = 4 * 3 * 5 * ... * 733 * 983 - 1` is a 1020 bit prime, the last prime being the smallest
+/// one that makes `p` prime. Exponents are sampled from `-2..=2`, giving about `2^301` keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Csidh1024;
+
+impl CsidhParams for Csidh1024 {
+    type Limbs = [u64; 16];
+    type WideLimbs = [u64; 17];
+    type SignedLimbs = [i64; 17];
+    type Exponents = [i8; 130];
+    type Bytes = [u8; 128];
+    type KeyBytes = [u8; 130];
+    type PackedKeyBytes = [u8; 65];
+
+    const PBITS: u64 = 1020;
+    const MAX_EXPONENT: i8 = 2;
+
+    const PRIMES: &'static [u64] = &[
+        3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+        101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191,
+        193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283,
+        293, 307, 311, 313, 317, 331, 337, 347, 349, 353, 359, 367, 373, 379, 383, 389, 397, 401,
+        409, 419, 421, 431, 433, 439, 443, 449, 457, 461, 463, 467, 479, 487, 491, 499, 503, 509,
+        521, 523, 541, 547, 557, 563, 569, 571, 577, 587, 593, 599, 601, 607, 613, 617, 619, 631,
+        641, 643, 647, 653, 659, 661, 673, 677, 683, 691, 701, 709, 719, 727, 733, 983
+    ];
+
+    const STRATEGY: &'static [i16] = &[
+        -55, -30, -18, -12, -8, -6, -5, -4, -3, -2, 1, -1, -2, -1, -1, -3, -1, -1, 2, -1, -5, -2,
+        -1, -1, -1, -3, -1, -1, -1, -1, -1, -9, -3, -1, -1, -2, -1, -1, -1, -1, -5, -1, -1, -1, -1,
+        -3, -1, -1, -2, -1, 5, 4, -1, -1, -1, -19, -5, -1, -1, -1, -1, -4, -1, -1, -1, -2, -1, -1,
+        -1, -1, -1, -1, -1, -1, -11, -2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -8, -1, -1, -1, -1, -1,
+        -1, -1, -7, 6, 5, 4, 3, -1, -1, -6, -1, -1, -1, -1, -1, -5, -1, -1, -1, -1, -4, -1, -1, -1,
+        -3, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1
+    ];
+
+    const INV_MIN_P_MOD_R: u64 = 0xd2c2c24160038025;
+
+    const P: [u64; 16] = [
+        0xdbe34c5460e36453, 0xa1d81eebbc3d344d, 0x514ba72cb8d89fd3, 0xc2cab6a0e287f1bd,
+        0x642aca4d5a313709, 0x6b317c5431541f40, 0xb97c56d1de81ede5, 0x0978dbeed90a2b58,
+        0x7611ad4f90441c80, 0xf811d9c419ec8329, 0x4d6c594a8ad82d2d, 0xf06de2471cf9386e,
+        0x0683cf25db31ad5b, 0x216c22bc86f21a08, 0xd89dec879007ebd7, 0x0ece55ed427012a9,
+    ];
+
+    const P_MINUS_2: [u64; 16] = [
+        0xdbe34c5460e36451, 0xa1d81eebbc3d344d, 0x514ba72cb8d89fd3, 0xc2cab6a0e287f1bd,
+        0x642aca4d5a313709, 0x6b317c5431541f40, 0xb97c56d1de81ede5, 0x0978dbeed90a2b58,
+        0x7611ad4f90441c80, 0xf811d9c419ec8329, 0x4d6c594a8ad82d2d, 0xf06de2471cf9386e,
+        0x0683cf25db31ad5b, 0x216c22bc86f21a08, 0xd89dec879007ebd7, 0x0ece55ed427012a9,
+    ];
+
+    const P_MINUS_1_HALVES: [u64; 16] = [
+        0xedf1a62a3071b229, 0xd0ec0f75de1e9a26, 0xa8a5d3965c6c4fe9, 0xe1655b507143f8de,
+        0x32156526ad189b84, 0xb598be2a18aa0fa0, 0x5cbe2b68ef40f6f2, 0x04bc6df76c8515ac,
+        0xbb08d6a7c8220e40, 0xfc08ece20cf64194, 0x26b62ca5456c1696, 0xf836f1238e7c9c37,
+        0x0341e792ed98d6ad, 0x90b6115e43790d04, 0xec4ef643c803f5eb, 0x07672af6a1380954,
+    ];
+
+    const R_SQUARED_MOD_P: [u64; 16] = [
+        0xd6b8f146ec5055af, 0x68ac5d7707ccb03a, 0x1322c9b9837dca17, 0x4f2940830c1d2b35,
+        0x8c1a56e5bf96471a, 0x6cdde00636c4f801, 0x9365ec4fa327c9ac, 0xa0056a67c1de0e82,
+        0x8aa6fa7e6811faa8, 0x9aad9631bb760403, 0x156b34c683839b9d, 0xa5ae047480992b2c,
+        0xc124d930289048b5, 0x4f8a8344bbe56288, 0xe1a2eb1d838b8237, 0x057162f911ca93a3,
+    ];
+
+    const R_MOD_P: [u64; 16] = [
+        0x65e7ee6590e6567d, 0x40a5f2587fef86d4, 0x99f9e607b99d62f2, 0x1089df50f4f8f26d,
+        0x592890dd02bb585a, 0xe1b6be68b969ecb9, 0xaebe3c10395f33c3, 0x5ef9652396531f1b,
+        0x28d37db76b7a1b7f, 0x86d089fa474b4a3f, 0xdbce120cc7a4fff2, 0x08b3f947137340ac,
+        0x913f3e7c71b37ce5, 0xc7d1b17b09ec4577, 0x9d834aff6f7956b6, 0x044c4b3e968ec2b8,
+    ];
+
+    const FOUR_SQRT_P: [u64; 16] = [
+        0xeba75c5815bb0d57, 0xfec8564a9ae457c6, 0xe362e1c2334bd738, 0x56f74a246ef0a30e,
+        0x4a598c9571aeb858, 0xc5617b211ccad355, 0x4fb69e4928ccc442, 0xf643475c7915859c,
+        0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+        0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    ];
+}
+
+/// CSIDH-1792, the largest of the proposed parameter sets
+///
+/// `p = 4 * 3 * 5 * ... * 1279 * 1619 - 1` is a 1787 bit prime, the last prime being the
+/// smallest one that makes `p` prime. Exponents are sampled from `-1..=1`, giving about `2^328`
+/// keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Csidh1792;
+
+impl CsidhParams for Csidh1792 {
+    type Limbs = [u64; 28];
+    type WideLimbs = [u64; 29];
+    type SignedLimbs = [i64; 29];
+    type Exponents = [i8; 207];
+    type Bytes = [u8; 224];
+    type KeyBytes = [u8; 207];
+    type PackedKeyBytes = [u8; 104];
+
+    const PBITS: u64 = 1787;
+    const MAX_EXPONENT: i8 = 1;
+
+    const PRIMES: &'static [u64] = &[
+        3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+        101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191,
+        193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283,
+        293, 307, 311, 313, 317, 331, 337, 347, 349, 353, 359, 367, 373, 379, 383, 389, 397, 401,
+        409, 419, 421, 431, 433, 439, 443, 449, 457, 461, 463, 467, 479, 487, 491, 499, 503, 509,
+        521, 523, 541, 547, 557, 563, 569, 571, 577, 587, 593, 599, 601, 607, 613, 617, 619, 631,
+        641, 643, 647, 653, 659, 661, 673, 677, 683, 691, 701, 709, 719, 727, 733, 739, 743, 751,
+        757, 761, 769, 773, 787, 797, 809, 811, 821, 823, 827, 829, 839, 853, 857, 859, 863, 877,
+        881, 883, 887, 907, 911, 919, 929, 937, 941, 947, 953, 967, 971, 977, 983, 991, 997, 1009,
+        1013, 1019, 1021, 1031, 1033, 1039, 1049, 1051, 1061, 1063, 1069, 1087, 1091, 1093, 1097,
+        1103, 1109, 1117, 1123, 1129, 1151, 1153, 1163, 1171, 1181, 1187, 1193, 1201, 1213, 1217,
+        1223, 1229, 1231, 1237, 1249, 1259, 1277, 1279, 1619
+    ];
+
+    const STRATEGY: &'static [i16] = &[
+        -77, -39, -24, -15, -11, -8, -6, -5, -4, -3, -2, 1, -1, -2, -1, -2, -1, -1, -4, -2, -1, -1,
+        -2, -1, -1, -1, -6, -2, -1, -1, -1, -1, -3, -1, -1, -2, -1, -1, -1, -1, -11, -3, -1, -1, -2,
+        -1, -1, -1, -1, -1, -1, -6, 5, 4, 3, -1, -1, -5, -1, -1, -1, -1, -4, -1, -1, -1, -3, -1, -1,
+        -2, -1, -1, -1, -1, -1, -1, -1, -25, -6, -1, -1, -1, -1, -1, -4, -1, -1, -1, -3, -1, -1, -2,
+        -1, 9, 8, 7, 6, 5, 4, -1, -1, -1, -16, -3, -1, -1, -2, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -12, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -11, -1, -1, -1, -1, -1, -1, -1,
+        -1, -1, -1, -10, -1, -1, -1, -1, -1, -1, -1, -1, -1, -9, -1, -1, -1, -1, -1, -1, -1, -1, -8,
+        -1, -1, -1, -1, -1, -1, -1, -7, 6, 5, 4, 3, -1, -1, -5, -1, -1, -1, -1, -4, -1, -1, -1, -3,
+        -1, -1, -2, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1
+    ];
+
+    const INV_MIN_P_MOD_R: u64 = 0x22c128875550b26d;
+
+    const P: [u64; 28] = [
+        0x5d9b07bf08f8d89b, 0x25aac2691b49f487, 0x7b040783abaa0dc6, 0xcf0c03fa6a4178f5,
+        0x51d9c6969089aa7d, 0xce2a612f570ded8e, 0x05522b2fe89455a0, 0x37ad61382711dec5,
+        0xf903792cf37605fa, 0x0db5895154051035, 0xcea4817d32d9dd75, 0xd5613f0e31766325,
+        0x3548a2aab0114853, 0xe14e9c7216d48555, 0x59aa445809df18d7, 0xeee62502a788494d,
+        0x7e2e9d4cf7bd24e6, 0x3a5f7628997ec1c0, 0x913bfef7378a5ce1, 0xbde70d8ed45236fc,
+        0xbaf0cb9688335e6d, 0x783424ee0e2a9bde, 0x4902e46f7d2533be, 0x35c5350f7ab21a7f,
+        0xc1d504d2f37afcbd, 0x1cd64b8b38a2b2b4, 0xa5428a204d7b7c8e, 0x070fe581dfd93790,
+    ];
+
+    const P_MINUS_2: [u64; 28] = [
+        0x5d9b07bf08f8d899, 0x25aac2691b49f487, 0x7b040783abaa0dc6, 0xcf0c03fa6a4178f5,
+        0x51d9c6969089aa7d, 0xce2a612f570ded8e, 0x05522b2fe89455a0, 0x37ad61382711dec5,
+        0xf903792cf37605fa, 0x0db5895154051035, 0xcea4817d32d9dd75, 0xd5613f0e31766325,
+        0x3548a2aab0114853, 0xe14e9c7216d48555, 0x59aa445809df18d7, 0xeee62502a788494d,
+        0x7e2e9d4cf7bd24e6, 0x3a5f7628997ec1c0, 0x913bfef7378a5ce1, 0xbde70d8ed45236fc,
+        0xbaf0cb9688335e6d, 0x783424ee0e2a9bde, 0x4902e46f7d2533be, 0x35c5350f7ab21a7f,
+        0xc1d504d2f37afcbd, 0x1cd64b8b38a2b2b4, 0xa5428a204d7b7c8e, 0x070fe581dfd93790,
+    ];
+
+    const P_MINUS_1_HALVES: [u64; 28] = [
+        0xaecd83df847c6c4d, 0x12d561348da4fa43, 0xbd8203c1d5d506e3, 0xe78601fd3520bc7a,
+        0x28ece34b4844d53e, 0x67153097ab86f6c7, 0x82a91597f44a2ad0, 0x1bd6b09c1388ef62,
+        0xfc81bc9679bb02fd, 0x86dac4a8aa02881a, 0xe75240be996ceeba, 0xeab09f8718bb3192,
+        0x9aa451555808a429, 0xf0a74e390b6a42aa, 0xacd5222c04ef8c6b, 0x7773128153c424a6,
+        0x3f174ea67bde9273, 0x9d2fbb144cbf60e0, 0x489dff7b9bc52e70, 0xdef386c76a291b7e,
+        0x5d7865cb4419af36, 0x3c1a127707154def, 0xa4817237be9299df, 0x9ae29a87bd590d3f,
+        0x60ea826979bd7e5e, 0x0e6b25c59c51595a, 0x52a1451026bdbe47, 0x0387f2c0efec9bc8,
+    ];
+
+    const R_SQUARED_MOD_P: [u64; 28] = [
+        0x2ee2feae4c6d30c0, 0x6a1f63c8f2d68fdd, 0x782eea8d24389e44, 0x69023be5b97339bb,
+        0x06c1df338b574980, 0x7bba66854f8e134a, 0xfbda2879809f4037, 0x070c9fa8823d8c57,
+        0x168dfd02d3164051, 0xf95a9a4ba710918b, 0x87319d3cb525fb9e, 0xec96ecaacd00d688,
+        0x1efd5678c0db8169, 0xb21dda72df112135, 0x4070b7df5bd8394b, 0x3af8189c9361546d,
+        0x82661223343d79d8, 0xd35da9bb8aa45606, 0x8ad8f53054d1f63d, 0x1273c77900f84215,
+        0x537f2f1983d31d00, 0x2ee4db7dedd10d81, 0x6ac1c4a30b998fba, 0xe9abfe6fd58dfcc1,
+        0x4d00a5d1cd8c2db7, 0x72a3741a4c61e493, 0x2f03a27cd3b91caf, 0x01c540a79797d7e8,
+    ];
+
+    const R_MOD_P: [u64; 28] = [
+        0xd632e922bd018a34, 0xb3fca93829999cf6, 0xb36ef17bdc161022, 0xe24f70c90ecafd7a,
+        0x7d6012d3aca4064e, 0x020a5557c20a97fc, 0x4071ed434b23f563, 0x2b9e541a817cac4b,
+        0xfb82f5adc36728d0, 0x1278b0902f49b868, 0xf0ddca64d95cdb8a, 0xfe5322010b5a0eae,
+        0x81c91fff3d91d435, 0x50f1fff4ca1d4004, 0x640e639e9ca081a4, 0x67a2cba070d5b11f,
+        0x4171e12d2966cf86, 0xca93624a6a2cc0ee, 0x9390253c308af053, 0x4b8217ea2470447b,
+        0xb6235ed4d8c6b891, 0x18aace86020214ad, 0xbb97e05266c4b937, 0x704489d2bef44619,
+        0xbe0b5255c2b47564, 0xf1dd606c091ede94, 0xc2a493751aa27c03, 0x01c3b9bc85742fa8,
+    ];
+
+    const FOUR_SQRT_P: [u64; 28] = [
+        0x786a14afe2c89a34, 0x9925964f3b8ba85f, 0x673f89909680766b, 0xd71a0b6db06fdac2,
+        0x5257b3c9f41e235f, 0xc7b9829c36fb9345, 0x80bf636872ad11b1, 0xa08ec86f7c8740e2,
+        0x46f32648f93e1cc8, 0x43d4c14a1946eee0, 0x75ed7f488c354091, 0x8486cbfa64e4aef3,
+        0xcd213274966cbe9a, 0xaa13d4c44e01a370, 0x0000000000000000, 0x0000000000000000,
+        0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+        0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+        0x0000000000000000, 0x0000000000000000, 0x0000000000000000, 0x0000000000000000,
+    ];
+}
+
+/// A toy parameter set with `p = 4 * 3 * 5 * 7 * 47 - 1 = 19739`, for tests only
+///
+/// The class group has 183 elements and is generated by the ideal above 3, which makes it small
+/// enough to be walked completely.
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Toy;
+
+#[cfg(test)]
+impl CsidhParams for Toy {
+    type Limbs = [u64; 1];
+    type WideLimbs = [u64; 2];
+    type SignedLimbs = [i64; 1];
+    type Exponents = [i8; 4];
+    type Bytes = [u8; 8];
+    type KeyBytes = [u8; 4];
+    type PackedKeyBytes = [u8; 2];
+
+    const PBITS: u64 = 15;
+    const MAX_EXPONENT: i8 = 5;
+
+    const PRIMES: &'static [u64] = &[3, 5, 7, 47];
+
+    const STRATEGY: &'static [i16] = &[-3, -2, 1];
+
+    const INV_MIN_P_MOD_R: u64 = 0x04ea54111149baed;
+
+    const P: [u64; 1] = [0x4d1b];
+    const P_MINUS_2: [u64; 1] = [0x4d19];
+    const P_MINUS_1_HALVES: [u64; 1] = [0x268d];
+    const R_SQUARED_MOD_P: [u64; 1] = [0x3b77];
+    const R_MOD_P: [u64; 1] = [0x1661];
+    const FOUR_SQRT_P: [u64; 1] = [561];
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::galois::{GaloisElement, LargeUint};
+
+    fn check_params<P: CsidhParams>() {
+        let p = LargeUint::<P> { elements: P::P };
+        assert_eq!(P::PBITS, p.bits());
+        assert_eq!(P::PRIMES.len(), P::NUM_PRIMES);
+        assert_eq!(P::STRATEGY.len(), P::NUM_PRIMES - 1);
+        assert_eq!(<P::WideLimbs as Array<u64>>::LEN, P::LIMBS + 1);
+        assert_eq!(<P::SignedLimbs as Array<i64>>::LEN as u64, (P::PBITS + 1).div_ceil(62));
+        assert_eq!(<P::Bytes as Array<u8>>::LEN, 8 * P::LIMBS);
+        assert_eq!(<P::KeyBytes as Array<u8>>::LEN, P::NUM_PRIMES);
+        assert_eq!(<P::PackedKeyBytes as Array<u8>>::LEN, P::NUM_PRIMES.div_ceil(2));
+
+        let mut four_l = LargeUint::<P>::from_u64(4);
+        for &l in P::PRIMES {
+            four_l.mul_with_u64(l);
+        }
+        let mut p_plus_1 = p;
+        p_plus_1.add_from(&LargeUint::from_u64(1));
+        assert_eq!(four_l, p_plus_1);
+
+        assert_eq!(P::P[0].wrapping_mul(P::INV_MIN_P_MOD_R), u64::MAX);
+
+        let four_sqrt_p = LargeUint::<P> { elements: P::FOUR_SQRT_P };
+        assert_eq!(four_sqrt_p.bits(), P::PBITS.div_ceil(2) + 2);
+
+        let one = GaloisElement::<P>::from_u64(1);
+        assert_eq!(one.elements, P::R_MOD_P);
+        assert_eq!(one.into_large_uint(), LargeUint::from_u64(1));
+    }
+
+    /// Finds the strategy with the lowest cost by dynamic programming over all ranges of primes
+    ///
+    /// Multiplying a point by `l` costs about 12 multiplications per bit, pushing a point through
+    /// an isogeny of degree `l` about `2 * l`.
+    fn optimal_strategy(primes: &[u64]) -> Vec<i16> {
+        let n = primes.len();
+        let mut mul = vec![0u64; n + 1];
+        let mut push = vec![0u64; n + 1];
+        for (i, &l) in primes.iter().enumerate() {
+            mul[i + 1] = mul[i] + 12 * (64 - l.leading_zeros() as u64);
+            push[i + 1] = push[i] + 2 * l;
+        }
+
+        // cost[i][j] and split[i][j] belong to the primes i..j
+        let mut cost = vec![vec![0u64; n + 1]; n];
+        let mut split = vec![vec![0i16; n + 1]; n];
+        for len in 2..=n {
+            for i in 0..=(n - len) {
+                let j = i + len;
+                let mut best = u64::MAX;
+                for m in (i + 1)..j {
+                    let both = cost[i][m] + cost[m][j];
+                    let right_first = both + mul[m] - mul[i] + push[j] - push[m];
+                    let left_first = both + mul[j] - mul[m] + push[m] - push[i];
+                    if right_first < best {
+                        best = right_first;
+                        split[i][j] = (m - i) as i16;
+                    }
+                    if left_first < best {
+                        best = left_first;
+                        split[i][j] = -((m - i) as i16);
+                    }
+                }
+                cost[i][j] = best;
+            }
+        }
+
+        fn preorder(split: &[Vec<i16>], i: usize, j: usize, out: &mut Vec<i16>) {
+            if j - i > 1 {
+                let m = i + split[i][j].unsigned_abs() as usize;
+                out.push(split[i][j]);
+                preorder(split, i, m, out);
+                preorder(split, m, j, out);
+            }
+        }
+
+        let mut strategy = Vec::new();
+        preorder(&split, 0, n, &mut strategy);
+        strategy
+    }
+
+    #[test]
+    fn check_strategies() {
+        assert_eq!(Csidh512::STRATEGY, &optimal_strategy(Csidh512::PRIMES)[..]);
+        assert_eq!(Csidh1024::STRATEGY, &optimal_strategy(Csidh1024::PRIMES)[..]);
+        assert_eq!(Csidh1792::STRATEGY, &optimal_strategy(Csidh1792::PRIMES)[..]);
+        assert_eq!(Toy::STRATEGY, &optimal_strategy(Toy::PRIMES)[..]);
+    }
+
+    #[test]
+    fn check_csidh_512() {
+        check_params::<Csidh512>();
+    }
+
+    #[test]
+    fn check_csidh_1024() {
+        check_params::<Csidh1024>();
+    }
+
+    #[test]
+    fn check_csidh_1792() {
+        check_params::<Csidh1792>();
+    }
+
+    #[test]
+    fn check_toy() {
+        check_params::<Toy>();
+    }
+}
@@ -0,0 +1,352 @@
+//! SeaSign, the Fiat–Shamir with aborts signatures of De Feo and Galbraith
+//!
+//! Unlike CSI-FiSh this works with exponent vectors directly and needs nothing about the class
+//! group. The signer has private keys `e_1, ..., e_n` with public curves `E_j = [e_j] E_0`, and
+//! `E_-j`, the quadratic twist of `E_j`, is `[-e_j] E_0`. Each round commits to `[f] E_0` for a
+//! random `f` and answers the challenge `c` with `z = f - e_c`. To hide `e_c`, `f` is drawn from a
+//! larger range than the responses are allowed to take, and the whole signature is started over
+//! whenever a response falls outside of that range.
+
+use rand::distributions::{Distribution, Uniform};
+use rand::prelude::*;
+use zeroize::Zeroizing;
+
+use crate::csidh::{action, action_constant_time, ActionMode, IsogenyConfig, PrivateKey};
+use crate::csifish::{challenge_curve, challenges, hash, CsiFishPublicKey, HASH_BYTES};
+use crate::error::CsidhError;
+use crate::galois::LargeUint;
+use crate::keyspace::KeySpace;
+use crate::montgomery::Curve;
+use crate::params::{Array, Csidh512, CsidhParams};
+
+/// SeaSign signatures with the parameter set `P`
+///
+/// The private keys have exponents in `-key_bound..=key_bound`, the commitments in
+/// `-127..=127` and the responses have to stay in `-(127 - key_bound)..=(127 - key_bound)`, so
+/// that they fit into exponents. A public key has `curves` curves and a signature answers `rounds`
+/// challenges, each one of `2 * curves + 1` possibilities.
+///
+/// With responses this small, every exponent of every round is rejected with a probability of
+/// about `key_bound / 127`, see `acceptance_probability`. This is meant for comparisons with
+/// CSI-FiSh, not for production use. The keys and commitments are evaluated with the constant-time
+/// action, for a commitment that is `127` isogenies of every degree. Signing gives up with
+/// `CsidhError::SigningAborted` after `max_attempts` aborted attempts, 1000 unless set with
+/// `with_max_attempts`.
+///
+/// ## Example
+///
+/// With two rounds for CSIDH-512 an attempt goes through with a probability of about 0.31, so
+/// signing takes about three attempts. Sixteen rounds would give a challenge space worth the name,
+/// but take about 11000 attempts.
+///
+/// ```rust,no_run
+/// # use csidh::SeaSign;
+/// let scheme: SeaSign = SeaSign::new(1, 255, 2).unwrap();
+/// let (private, public) = scheme.generate_keys(&mut rand::thread_rng());
+///
+/// let signature = scheme.sign(&private, b"message").unwrap();
+/// assert!(scheme.verify(&public, b"message", &signature));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeaSign<P: CsidhParams = Csidh512> {
+    key_bound: i8,
+    curves: usize,
+    rounds: usize,
+    max_attempts: usize,
+    params: std::marker::PhantomData<P>,
+}
+
+impl<P: CsidhParams> SeaSign<P> {
+    /// SeaSign with private keys bounded by `key_bound`, `curves` public curves and `rounds`
+    /// challenges per signature
+    ///
+    /// The key bound has to be between `1` and `63`, there have to be between one and `32767`
+    /// curves and at least one round.
+    pub fn new(key_bound: i8, curves: usize, rounds: usize) -> Result<SeaSign<P>, CsidhError> {
+        if !(1..=63).contains(&key_bound) || !(1..=i16::MAX as usize).contains(&curves)
+            || rounds == 0
+        {
+            return Err(CsidhError::InvalidParameters);
+        }
+
+        Ok(SeaSign {
+            key_bound,
+            curves,
+            rounds,
+            max_attempts: 1000,
+            params: std::marker::PhantomData,
+        })
+    }
+
+    /// Sets how many aborted attempts a signature may take before signing fails
+    pub fn with_max_attempts(mut self, attempts: usize) -> SeaSign<P> {
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// The largest absolute value of an exponent of a response
+    pub fn response_bound(&self) -> i8 {
+        i8::MAX - self.key_bound
+    }
+
+    /// The probability that a signature attempt is not aborted
+    pub fn acceptance_probability(&self) -> f64 {
+        let accepted = 2.0 * self.response_bound() as f64 + 1.0;
+        let committed = 2.0 * i8::MAX as f64 + 1.0;
+        (accepted / committed).powi((P::NUM_PRIMES * self.rounds) as i32)
+    }
+
+    /// The length of a signature in bytes
+    pub fn signature_bytes(&self) -> usize {
+        HASH_BYTES + self.rounds * P::NUM_PRIMES
+    }
+
+    /// Generates a new key pair
+    pub fn generate_keys<R: CryptoRng + Rng>(&self, rng: &mut R)
+        -> (SeaSignPrivateKey<P>, SeaSignPublicKey<P>)
+    {
+        let key_space = KeySpace::uniform(self.key_bound).expect("the key bound is valid");
+        let keys: Vec<PrivateKey<P>> = (0..self.curves)
+            .map(|_| {
+                PrivateKey::generate_new(&key_space, rng).with_action_mode(ActionMode::ConstantTime)
+            })
+            .collect();
+
+        let curves = keys.iter().map(|key| key.get_public_key_with_rng(rng)).collect();
+
+        (SeaSignPrivateKey { keys }, SeaSignPublicKey(CsiFishPublicKey { curves }))
+    }
+
+    /// Signs `message`
+    ///
+    /// Fails with `CsidhError::SigningAborted` if all of the `max_attempts` attempts are aborted,
+    /// and with `CsidhError::InvalidParameters` if the key was generated with a different number
+    /// of curves.
+    pub fn sign(&self, private: &SeaSignPrivateKey<P>, message: &[u8])
+        -> Result<SeaSignSignature, CsidhError>
+    {
+        self.sign_with_rng(private, message, &mut thread_rng())
+    }
+
+    /// Signs `message`, drawing the commitments and the randomness for the group action from
+    /// `rng`
+    pub fn sign_with_rng<R: CryptoRng + Rng>(&self, private: &SeaSignPrivateKey<P>,
+                                             message: &[u8], rng: &mut R)
+        -> Result<SeaSignSignature, CsidhError>
+    {
+        if private.keys.len() != self.curves {
+            return Err(CsidhError::InvalidParameters);
+        }
+
+        let commitment = Uniform::from(-i8::MAX..=i8::MAX);
+        let bounds = P::Exponents::filled(i8::MAX);
+        let bound = self.response_bound() as i16;
+
+        for _ in 0..self.max_attempts {
+            let mut commitments = Zeroizing::new(vec![P::Exponents::filled(0); self.rounds]);
+            for f in commitments.iter_mut() {
+                for e in f.as_mut().iter_mut() {
+                    *e = commitment.sample(rng);
+                }
+            }
+
+            // The responses z = f - e are published, so the commitments must not leak through the
+            // timing of the action
+            let curves: Vec<LargeUint<P>> = commitments.iter()
+                .map(|f| {
+                    action_constant_time(&Curve::new(LargeUint::from_u64(0), 1u32.into()), f,
+                                         &bounds, IsogenyConfig::default(), rng)
+                        .expect("the starting curve is supersingular")
+                })
+                .collect();
+
+            let hash = hash(&curves, message);
+
+            let mut bytes = Zeroizing::new(hash.to_vec());
+            let mut accepted = true;
+            for (f, c) in commitments.iter().zip(challenges(&hash, self.curves, self.rounds)) {
+                let zero = P::Exponents::filled(0);
+                let (key, sign) = match c {
+                    0 => (&zero, 0),
+                    c if c > 0 => (private.keys[c as usize - 1].exponents(), 1),
+                    c => (private.keys[(-c) as usize - 1].exponents(), -1),
+                };
+
+                for (&f, &e) in f.as_ref().iter().zip(key.as_ref().iter()) {
+                    let z = f as i16 - sign * e as i16;
+                    accepted &= (-bound..=bound).contains(&z);
+                    bytes.push(z as i8 as u8);
+                }
+            }
+
+            if accepted {
+                return Ok(SeaSignSignature {
+                    bytes: bytes.to_vec(),
+                });
+            }
+        }
+
+        Err(CsidhError::SigningAborted)
+    }
+
+    /// Checks that `signature` is a valid signature of `message` under `public`
+    ///
+    /// Public keys that do not consist of supersingular curves never verify a signature.
+    pub fn verify(&self, public: &SeaSignPublicKey<P>, message: &[u8],
+                  signature: &SeaSignSignature) -> bool
+    {
+        self.verify_with_rng(public, message, signature, &mut thread_rng())
+    }
+
+    /// Like `verify`, but draws the randomness for the validation and the group action from `rng`
+    pub fn verify_with_rng<R: CryptoRng + Rng>(&self, public: &SeaSignPublicKey<P>,
+                                               message: &[u8], signature: &SeaSignSignature,
+                                               rng: &mut R) -> bool
+    {
+        let curves = &public.0.curves;
+        let bytes = signature.as_bytes();
+        if curves.len() != self.curves || bytes.len() != self.signature_bytes() {
+            return false;
+        }
+
+        let (expected, responses) = bytes.split_at(HASH_BYTES);
+        let bound = self.response_bound();
+        if !responses.iter().all(|&z| (-bound..=bound).contains(&(z as i8))) {
+            return false;
+        }
+
+        if !curves.iter().all(|curve| curve.validate_with_rng(rng)) {
+            return false;
+        }
+
+        let mut committed = Vec::with_capacity(self.rounds);
+        let challenges = challenges(expected, self.curves, self.rounds);
+        for (z, c) in responses.chunks(P::NUM_PRIMES).zip(challenges) {
+            let mut exponents = P::Exponents::filled(0);
+            for (e, &z) in exponents.as_mut().iter_mut().zip(z.iter()) {
+                *e = z as i8;
+            }
+
+            let curve = Curve::new(challenge_curve(curves, c), 1u32.into());
+            match action(&curve, &exponents, IsogenyConfig::public(), rng) {
+                Ok(curve) => committed.push(curve),
+                Err(_) => return false,
+            }
+        }
+
+        hash(&committed, message)[..] == *expected
+    }
+}
+
+/// A SeaSign private key, one CSIDH private key per public curve
+pub struct SeaSignPrivateKey<P: CsidhParams = Csidh512> {
+    keys: Vec<PrivateKey<P>>,
+}
+
+/// A SeaSign public key, a list of curves encoded like a CSI-FiSh public key
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeaSignPublicKey<P: CsidhParams = Csidh512>(CsiFishPublicKey<P>);
+
+impl<P: CsidhParams> SeaSignPublicKey<P> {
+    /// Encodes the public key, the curves are encoded like `PublicKey::to_bytes` one after the
+    /// other
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes()
+    }
+
+    /// Decodes a public key produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<SeaSignPublicKey<P>, CsidhError> {
+        CsiFishPublicKey::from_bytes(bytes).map(SeaSignPublicKey)
+    }
+}
+
+/// A SeaSign signature
+///
+/// The encoding is the 32 byte hash the challenges are derived from, followed by the response
+/// exponents of each round as bytes in two's complement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeaSignSignature {
+    bytes: Vec<u8>,
+}
+
+impl SeaSignSignature {
+    /// The encoded signature
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Wraps an encoded signature, its length is checked when it is verified
+    pub fn from_bytes(bytes: &[u8]) -> SeaSignSignature {
+        SeaSignSignature {
+            bytes: bytes.to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::Toy;
+
+    #[test]
+    fn check_sign() {
+        let mut rng = thread_rng();
+        let scheme = SeaSign::<Toy>::new(1, 3, 10).unwrap();
+        assert!(scheme.acceptance_probability() > 0.7);
+        let (private, public) = scheme.generate_keys(&mut rng);
+        assert!(private.keys.iter().all(|key| key.exponents().iter().all(|e| e.abs() <= 1)));
+
+        let signature = scheme.sign(&private, b"message").unwrap();
+        assert_eq!(signature.as_bytes().len(), 32 + 10 * 4);
+        assert!(signature.as_bytes()[32..].iter().all(|&z| (z as i8).abs() <= 126));
+        assert!(scheme.verify(&public, b"message", &signature));
+        assert!(!scheme.verify(&public, b"massage", &signature));
+
+        // The key has one curve too few for these parameters
+        let wider = SeaSign::<Toy>::new(1, 4, 10).unwrap();
+        assert_eq!(wider.sign(&private, b"message"), Err(CsidhError::InvalidParameters));
+
+        let mut bytes = signature.as_bytes().to_vec();
+        assert!(!scheme.verify(&public, b"message", &SeaSignSignature::from_bytes(&bytes[1..])));
+        bytes[0] ^= 1;
+        assert!(!scheme.verify(&public, b"message", &SeaSignSignature::from_bytes(&bytes)));
+
+        // Responses outside of the bound would leak the key
+        let mut bytes = signature.as_bytes().to_vec();
+        bytes[32] = 127;
+        assert!(!scheme.verify(&public, b"message", &SeaSignSignature::from_bytes(&bytes)));
+
+        let decoded = SeaSignPublicKey::from_bytes(&public.to_bytes()).unwrap();
+        assert!(scheme.verify(&decoded, b"message", &signature));
+    }
+
+    #[test]
+    fn check_rejection() {
+        // With a key bound of 63 about half of the exponents are rejected, the signatures that
+        // come out still stay within the response bound
+        let mut rng = thread_rng();
+        let scheme = SeaSign::<Toy>::new(63, 1, 1).unwrap();
+        assert!((scheme.acceptance_probability() - (129f64 / 255.0).powi(4)).abs() < 1e-12);
+
+        let (private, public) = scheme.generate_keys(&mut rng);
+        for _ in 0..5 {
+            let signature = scheme.sign(&private, b"message").unwrap();
+            assert!(signature.as_bytes()[32..].iter().all(|&z| (z as i8).abs() <= 64));
+            assert!(scheme.verify(&public, b"message", &signature));
+        }
+
+        // Forty rounds are accepted with a probability of about 2^-157
+        let scheme = SeaSign::<Toy>::new(63, 1, 40).unwrap().with_max_attempts(3);
+        let (private, _) = scheme.generate_keys(&mut rng);
+        assert_eq!(scheme.sign(&private, b"message"), Err(CsidhError::SigningAborted));
+    }
+
+    #[test]
+    fn check_invalid() {
+        assert_eq!(SeaSign::<Toy>::new(0, 1, 1), Err(CsidhError::InvalidParameters));
+        assert_eq!(SeaSign::<Toy>::new(64, 1, 1), Err(CsidhError::InvalidParameters));
+        assert_eq!(SeaSign::<Toy>::new(1, 0, 1), Err(CsidhError::InvalidParameters));
+        assert_eq!(SeaSign::<Toy>::new(1, 1, 0), Err(CsidhError::InvalidParameters));
+        assert_eq!(SeaSign::<Csidh512>::new(1, 1, 1).unwrap().signature_bytes(), 32 + 74);
+    }
+}
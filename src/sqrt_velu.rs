@@ -0,0 +1,508 @@
+//! The √élu isogeny formulas of Bernstein, De Feo, Leroux and Smith
+//!
+//! Vélu's formulas visit all `(l - 1) / 2` multiples of the kernel point `P`. √élu only computes
+//! about `sqrt(l)` of them: the giant steps `I = {2b, 6b, 10b, ...}` and the baby steps
+//! `J = {1, 3, ..., 2b - 1}`, chosen so that `I + J` and `I - J` together cover every odd
+//! multiple below `4 * b * b'`. The few multiples `K` above that are handled directly.
+//!
+//! For `i` in `I` and `j` in `J` the product `(X - x([i + j] P)) * (X - x([i - j] P))` is a
+//! quadratic in `X` whose coefficients are biquadratic in `x([i] P)` and `x([j] P)`. The
+//! product over all pairs is therefore the resultant of `h_I(Z) = prod (Z - x([i] P))` and a
+//! polynomial `E_J(Z)` of degree `2b` that only depends on the baby steps.
+//!
+//! `E_J` is multiplied together along a product tree of the baby step quadratics. The resultant
+//! is the product of `E_J` over the roots of `h_I`, which a remainder tree evaluates: a product
+//! tree over the giant steps is built once per isogeny, and `E_J` is reduced modulo its nodes on
+//! the way down to the roots. With Karatsuba multiplication this takes `O(b^1.59 log b)` field
+//! operations per evaluated point instead of the `O(l)` of Vélu's formulas.
+
+use crate::field::{FieldUint, PrimeField};
+use crate::montgomery::ProjectivePoint;
+
+/// The leaves of a product tree hold up to this many roots, which are evaluated at with Horner's
+/// rule
+const LEAF_ROOTS: usize = 4;
+
+/// Below this length polynomials are multiplied with the schoolbook method
+const KARATSUBA_THRESHOLD: usize = 4;
+
+/// Multiplies two polynomials, the coefficients are in ascending order
+///
+/// Long factors are split in halves and multiplied with Karatsuba's method.
+fn poly_mul<F: PrimeField>(a: &[F], b: &[F]) -> Vec<F> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+
+    let mut ret = vec![F::zero(); a.len() + b.len() - 1];
+    if a.len().min(b.len()) < KARATSUBA_THRESHOLD {
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                ret[i + j].add_from(&(*x * *y));
+            }
+        }
+        return ret;
+    }
+
+    // A long factor is cut into pieces as long as the short one, so that the halves below are
+    // balanced
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    if long.len() >= 2 * short.len() {
+        for (i, piece) in long.chunks(short.len()).enumerate() {
+            for (j, c) in poly_mul(piece, short).iter().enumerate() {
+                ret[i * short.len() + j].add_from(c);
+            }
+        }
+        return ret;
+    }
+
+    // a b = a0 b0 + ((a0 + a1)(b0 + b1) - a0 b0 - a1 b1) Z^h + a1 b1 Z^2h
+    let h = long.len().div_ceil(2);
+    let (a0, a1) = a.split_at(h);
+    let (b0, b1) = b.split_at(h);
+    let low = poly_mul(a0, b0);
+    let high = poly_mul(a1, b1);
+    let mut mid = poly_mul(&poly_add(a0, a1), &poly_add(b0, b1));
+    for (i, c) in low.iter().enumerate() {
+        ret[i].add_from(c);
+        mid[i].sub_from(c);
+    }
+    for (i, c) in high.iter().enumerate() {
+        ret[i + 2 * h].add_from(c);
+        mid[i].sub_from(c);
+    }
+    for (i, c) in mid.iter().enumerate().take(ret.len() - h) {
+        ret[i + h].add_from(c);
+    }
+    ret
+}
+
+/// The first `n` coefficients of the product of two polynomials
+fn poly_mul_low<F: PrimeField>(a: &[F], b: &[F], n: usize) -> Vec<F> {
+    let a = &a[..a.len().min(n)];
+    let b = &b[..b.len().min(n)];
+    if a.len().min(b.len()) >= KARATSUBA_THRESHOLD {
+        let mut ret = poly_mul(a, b);
+        ret.truncate(n);
+        return ret;
+    }
+
+    let mut ret = vec![F::zero(); n.min((a.len() + b.len()).saturating_sub(1))];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate().take(n.saturating_sub(i)) {
+            ret[i + j].add_from(&(*x * *y));
+        }
+    }
+    ret
+}
+
+/// Adds two polynomials
+fn poly_add<F: PrimeField>(a: &[F], b: &[F]) -> Vec<F> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut ret = long.to_vec();
+    for (r, c) in ret.iter_mut().zip(short) {
+        r.add_from(c);
+    }
+    ret
+}
+
+/// Multiplies all polynomials along a balanced tree
+fn poly_product<F: PrimeField>(mut polys: Vec<Vec<F>>) -> Vec<F> {
+    if polys.is_empty() {
+        return vec![F::one()];
+    }
+
+    while polys.len() > 1 {
+        polys = polys.chunks(2)
+            .map(|c| if c.len() == 2 { poly_mul(&c[0], &c[1]) } else { c[0].clone() })
+            .collect();
+    }
+
+    polys.pop().unwrap()
+}
+
+/// Computes the power series inverse of `poly` modulo `Z^n` with Newton's method
+///
+/// The constant coefficient of `poly` has to be one.
+fn reciprocal<F: PrimeField>(poly: &[F], n: usize) -> Vec<F> {
+    let mut ret = vec![F::one()];
+    while ret.len() < n {
+        // ret = ret (2 - poly ret) doubles the precision
+        let k = n.min(2 * ret.len());
+        let mut err = poly_mul_low(poly, &ret, k);
+        err[0] = F::zero();
+        let correction = poly_mul_low(&ret, &err, k);
+
+        ret.resize(k, F::zero());
+        for (r, c) in ret.iter_mut().zip(correction.iter()) {
+            r.sub_from(c);
+        }
+    }
+    ret.truncate(n);
+    ret
+}
+
+/// A product tree over the linear factors `Z - x` of a set of roots
+///
+/// Every node holds the product of the factors below it and the reciprocal of its reversal,
+/// which turns the division by the node into two multiplications.
+struct ProductTree<F> {
+    poly: Vec<F>,
+    reciprocal: Vec<F>,
+    // The roots of a leaf, few enough to evaluate at directly
+    roots: Vec<F>,
+    children: Option<Box<(ProductTree<F>, ProductTree<F>)>>,
+}
+
+impl<F: PrimeField> ProductTree<F> {
+    /// Builds the tree over `roots`, so that polynomials with up to `len` coefficients can be
+    /// reduced by it
+    fn new(roots: &[F], len: usize) -> ProductTree<F> {
+        let (poly, children) = if roots.len() <= LEAF_ROOTS {
+            (poly_product(roots.iter().map(|&r| vec![F::zero() - r, F::one()]).collect()), None)
+        } else {
+            // A remainder modulo this node has fewer coefficients than its degree
+            let (left, right) = roots.split_at(roots.len() / 2);
+            let left = ProductTree::new(left, roots.len());
+            let right = ProductTree::new(right, roots.len());
+            (poly_mul(&left.poly, &right.poly), Some(Box::new((left, right))))
+        };
+
+        let reversed: Vec<F> = poly.iter().rev().cloned().collect();
+        let reciprocal = reciprocal(&reversed, len.saturating_sub(roots.len()));
+        let roots = if children.is_none() { roots.to_vec() } else { Vec::new() };
+        ProductTree { poly, reciprocal, roots, children }
+    }
+
+    /// Reduces `poly` modulo the polynomial of this node
+    fn rem(&self, poly: &[F]) -> Vec<F> {
+        let degree = self.poly.len() - 1;
+        if poly.len() <= degree {
+            return poly.to_vec();
+        }
+
+        // The quotient is the reversal of rev(poly) / rev(self.poly) modulo Z^n
+        let n = poly.len() - degree;
+        let reversed: Vec<F> = poly.iter().rev().take(n).cloned().collect();
+        let mut quotient = poly_mul_low(&reversed, &self.reciprocal, n);
+        quotient.reverse();
+
+        let mut ret = poly[..degree].to_vec();
+        for (r, c) in ret.iter_mut().zip(poly_mul_low(&quotient, &self.poly, degree).iter()) {
+            r.sub_from(c);
+        }
+        ret
+    }
+
+    /// Computes the product of `poly` over all roots, which is the resultant of the polynomial
+    /// of the tree and `poly`
+    ///
+    /// The values at the roots are the remainders at the leaves, `poly` is reduced along the
+    /// way down.
+    fn resultant(&self, poly: &[F]) -> F {
+        let poly = self.rem(poly);
+        match &self.children {
+            Some(children) => children.0.resultant(&poly) * children.1.resultant(&poly),
+            None => self.roots.iter().fold(F::one(), |acc, r| acc * poly_eval(&poly, r, false)),
+        }
+    }
+}
+
+/// Evaluates a polynomial at `z` with Horner's rule
+///
+/// With `reverse` the coefficients are read backwards, which evaluates `Z^n * poly(1 / Z)`.
+fn poly_eval<F: PrimeField>(poly: &[F], z: &F, reverse: bool) -> F {
+    let mut ret = F::zero();
+    let mut step = |c: &F| {
+        ret.mul_with(z);
+        ret.add_from(c);
+    };
+
+    if reverse {
+        poly.iter().for_each(&mut step);
+    } else {
+        poly.iter().rev().for_each(&mut step);
+    }
+    ret
+}
+
+/// Inverts all elements with a single inversion
+fn batch_inverse<F: PrimeField>(elements: &mut [F]) {
+    let mut prefix = Vec::with_capacity(elements.len());
+    let mut acc = F::one();
+    for e in elements.iter() {
+        prefix.push(acc);
+        acc.mul_with(e);
+    }
+
+    acc.inverse();
+
+    for (e, p) in elements.iter_mut().zip(prefix.iter()).rev() {
+        let inv = acc * *p;
+        acc.mul_with(e);
+        *e = inv;
+    }
+}
+
+/// Computes `count` points in an arithmetic progression, each one `step` after the previous
+fn multiples<F: PrimeField>(first: &ProjectivePoint<F>, second: &ProjectivePoint<F>,
+                            step: &ProjectivePoint<F>, count: usize) -> Vec<ProjectivePoint<F>>
+{
+    let mut ret: Vec<ProjectivePoint<F>> = Vec::with_capacity(count);
+    for i in 0..count {
+        let next = match i {
+            0 => *first,
+            1 => *second,
+            _ => ret[i - 1].add(step, &ret[i - 2]),
+        };
+        ret.push(next);
+    }
+    ret
+}
+
+/// The coefficients needed for the biquadratic of a baby step `x([j] P)` on the curve `a`
+struct BabyStep<F> {
+    a: F,
+    b: F,
+    c: F,
+    e: F,
+}
+
+impl<F: PrimeField> BabyStep<F> {
+    fn new(curve: &ProjectivePoint<F>, p: &ProjectivePoint<F>) -> BabyStep<F> {
+        let xx = p.x * p.x;
+        let zz = p.z * p.z;
+        let xz = p.x * p.z;
+
+        let mut c = curve.z * xz;
+        c.add_from(&{c});
+        let mut e = curve.z * (xx + zz);
+        let t = curve.x * xz;
+        e.add_from(&t);
+        e.add_from(&t);
+        e.add_from(&{e});
+
+        BabyStep {
+            a: curve.z * zz,
+            b: curve.z * xx,
+            c: F::zero() - c,
+            e: F::zero() - e,
+        }
+    }
+
+    /// The quadratic in `Z` with the roots `x([i + j] P)` and `x([i - j] P)` for `x([i] P) = Z`,
+    /// evaluated at `X = alpha`
+    ///
+    /// This is `X^2 F0 + X F1 + F2` for the biquadratics `F0 = (Z - x_j)^2`,
+    /// `F1 = -2 ((Z x_j + 1)(Z + x_j) + 2 A Z x_j)` and `F2 = (Z x_j - 1)^2` of the paper, up to
+    /// a factor that does not depend on `alpha`.
+    fn quadratic(&self, alpha: &ProjectivePoint<F>) -> Vec<F> {
+        let u = alpha.x * alpha.x;
+        let v = alpha.x * alpha.z;
+        let w = alpha.z * alpha.z;
+        let vc = v * self.c;
+
+        vec![
+            u * self.b + vc + w * self.a,
+            (u + w) * self.c + v * self.e,
+            u * self.a + vc + w * self.b,
+        ]
+    }
+}
+
+/// Like `Curve::isogeny_points`, but with the √élu formulas
+///
+/// The results are the same as those of `Curve::isogeny_points` up to the projective scaling.
+pub fn isogeny_points<F: PrimeField, const N: usize>(a: &mut ProjectivePoint<F>,
+                                                     points: &mut [ProjectivePoint<F>; N],
+                                                     k: &ProjectivePoint<F>, l: u64)
+{
+    let s = (l as usize - 1) / 2;
+    let b = (l - 1).isqrt() as usize / 2;
+    let b_prime = if b == 0 { 0 } else { (l as usize - 1) / (4 * b) };
+
+    let double_k = k.double2(a);
+
+    // The baby steps x([j] P) for odd j < 2b
+    let baby: Vec<BabyStep<F>> = multiples(k, &k.add(&double_k, k), &double_k, b)
+        .iter()
+        .map(|p| BabyStep::new(a, p))
+        .collect();
+
+    // The giant steps x([i] P) for i = 2b, 6b, 10b, ..., scaled to z = 1 with a single
+    // inversion, and the product tree of h_I over them. The polynomials reduced by it are
+    // products of b quadratics.
+    let mut giant = None;
+    if b_prime != 0 {
+        let first = k.ladder2(a, &F::Uint::from_u64(2 * b as u64));
+        let step = first.double2(a);
+        let giant_points = multiples(&first, &first.add(&step, &first), &step, b_prime);
+
+        let mut zs: Vec<F> = giant_points.iter().map(|p| p.z).collect();
+        batch_inverse(&mut zs);
+        let roots: Vec<F> = giant_points.iter().zip(zs.iter()).map(|(p, z)| p.x * *z).collect();
+        giant = Some(ProductTree::new(&roots, 2 * b + 1));
+    }
+
+    // The odd multiples 4 b b' + 1, ..., l - 2 are the same points as the even multiples
+    // 2, 4, ..., l - 1 - 4 b b'
+    let rest = multiples(&double_k, &double_k.double2(a), &double_k, s - 2 * b * b_prime);
+
+    // Returns prod (alpha.x * x_s - alpha.z * z_s) and prod (alpha.x * z_s - alpha.z * x_s) over
+    // all multiples, both with the same factor in front. The first one is only computed when
+    // `numerator` is set, the codomain does not need it.
+    let evaluate = |alpha: &ProjectivePoint<F>, numerator: bool| -> (F, F) {
+        let mut poly = poly_product(baby.iter().map(|j| j.quadratic(alpha)).collect());
+
+        let mut num = F::one();
+        let mut den = F::one();
+        if let Some(tree) = giant.as_ref() {
+            den = tree.resultant(&poly);
+            if numerator {
+                poly.reverse();
+                num = tree.resultant(&poly);
+            }
+        }
+
+        for p in rest.iter() {
+            if numerator {
+                num.mul_with(&(alpha.x * p.x - alpha.z * p.z));
+            }
+            den.mul_with(&(alpha.x * p.z - alpha.z * p.x));
+        }
+
+        (num, den)
+    };
+
+    for p in points.iter_mut() {
+        let (mut num, mut den) = evaluate(p, true);
+        p.x.mul_with(&num.square());
+        p.z.mul_with(&den.square());
+    }
+
+    // As for the Edwards formulas the image of (A + 2C : A - 2C) is
+    // ((A + 2C)^l * prod (x_s + z_s)^8 : (A - 2C)^l * prod (x_s - z_s)^8)
+    let one = F::one();
+    let (_, mut plus) = evaluate(&ProjectivePoint::new(F::zero() - one, one), false);
+    let (_, mut minus) = evaluate(&ProjectivePoint::new(one, one), false);
+    for _ in 0..3 {
+        plus.square();
+        minus.square();
+    }
+
+    let two_c = a.z + a.z;
+    let ed_a = (a.x + two_c).pow_u64(l) * plus;
+    let ed_d = (a.x - two_c).pow_u64(l) * minus;
+
+    a.x = ed_a + ed_d;
+    a.x.add_from(&{a.x});
+    a.z = ed_a - ed_d;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::galois::{GaloisElement, LargeUint};
+    use crate::montgomery::Curve;
+    use crate::params::{Csidh512, CsidhParams};
+
+    type F = GaloisElement<Csidh512>;
+
+    #[test]
+    fn check_batch_inverse() {
+        let mut elements: Vec<F> = (1..10).map(F::from_u64).collect();
+        batch_inverse(&mut elements);
+        for (i, e) in elements.iter().enumerate() {
+            assert_eq!(*e * F::from_u64(i as u64 + 1), F::one());
+        }
+    }
+
+    #[test]
+    fn check_poly() {
+        let a: Vec<F> = [1, 2].iter().map(|&c| F::from_u64(c)).collect();
+        let b: Vec<F> = [3, 0, 1].iter().map(|&c| F::from_u64(c)).collect();
+        let ab = poly_product(vec![a, b]);
+        assert_eq!(ab, [3, 6, 1, 2].iter().map(|&c| F::from_u64(c)).collect::<Vec<_>>());
+
+        let five = F::from_u64(5);
+        assert_eq!(poly_eval(&ab, &five, false), F::from_u64(3 + 6 * 5 + 25 + 2 * 125));
+        assert_eq!(poly_eval(&ab, &five, true), F::from_u64(3 * 125 + 6 * 25 + 5 + 2));
+    }
+
+    #[test]
+    fn check_karatsuba() {
+        let a: Vec<F> = (1..40).map(|c| F::from_u64(c * c + 7)).collect();
+        let b: Vec<F> = (1..23).map(|c| F::from_u64(3 * c + 1)).collect();
+        let ab = poly_mul(&a, &b);
+
+        let mut schoolbook = vec![F::zero(); a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                schoolbook[i + j].add_from(&(*x * *y));
+            }
+        }
+        assert_eq!(ab, schoolbook);
+    }
+
+    #[test]
+    fn check_reciprocal() {
+        let poly: Vec<F> = (1..20).map(F::from_u64).collect();
+        let inverse = reciprocal(&poly, 30);
+        let product = poly_mul(&poly, &inverse);
+        assert_eq!(product[0], F::one());
+        assert!(product[1..30].iter().all(|c| *c == F::zero()));
+    }
+
+    #[test]
+    fn check_resultant() {
+        let poly: Vec<F> = (1..26).map(|c| F::from_u64(c * 11 + 2)).collect();
+        for count in 1..14 {
+            let roots: Vec<F> = (0..count).map(|r| F::from_u64(r * r + 3)).collect();
+            let tree = ProductTree::new(&roots, poly.len());
+            let expected = roots.iter()
+                .fold(F::one(), |acc, r| acc * poly_eval(&poly, r, false));
+            assert_eq!(tree.resultant(&poly), expected, "{} roots", count);
+        }
+    }
+
+    #[test]
+    fn check_against_velu() {
+        let curve = ProjectivePoint::new(F::from_u64(0), F::from_u64(1));
+        let point = ProjectivePoint::new(F::from_u64(5), F::from_u64(1));
+        let twist = (1..)
+            .map(F::from_u64)
+            .find(|x| !Curve::right_side(&curve.x, x).is_square())
+            .map(|x| ProjectivePoint::new(x, F::from_u64(1)))
+            .unwrap();
+
+        for &l in Csidh512::PRIMES.iter() {
+            // (p + 1) / l kills everything but the l-torsion
+            let mut cof = LargeUint::from_u64(4);
+            for &other in Csidh512::PRIMES.iter().filter(|&&other| other != l) {
+                cof.mul_with_u64(other);
+            }
+
+            let kernel = (1..)
+                .map(F::from_u64)
+                .filter(|x| Curve::right_side(&curve.x, x).is_square())
+                .map(|x| ProjectivePoint::new(x, F::from_u64(1)).ladder2(&curve, &cof))
+                .find(|k| !k.is_infinity())
+                .unwrap();
+
+            let mut velu = (curve, [point, twist, kernel]);
+            Curve::isogeny_points(&mut velu.0, &mut velu.1, &kernel, l);
+            let mut sqrt_velu = (curve, [point, twist, kernel]);
+            isogeny_points(&mut sqrt_velu.0, &mut sqrt_velu.1, &kernel, l);
+
+            assert!(sqrt_velu.1[2].is_infinity());
+            velu.0.normalize();
+            sqrt_velu.0.normalize();
+            assert_eq!(velu.0, sqrt_velu.0, "l = {}", l);
+            for i in 0..2 {
+                velu.1[i].normalize();
+                sqrt_velu.1[i].normalize();
+                assert_eq!(velu.1[i], sqrt_velu.1[i], "l = {}", l);
+            }
+        }
+    }
+}